@@ -1,4 +1,5 @@
 use std::fmt;
+use crate::bigint::BigInt;
 use crate::token::Token;
 
 #[derive(Debug)]
@@ -23,17 +24,35 @@ pub type Program = Vec<Statement>;
 
 #[derive(Debug, Clone)]
 pub enum Statement {
-    Let(String, Expression),
+    Let(Pattern, Expression),
+    // `export` before a `let`, `struct` or `enum` declaration
+    Export(Box<Statement>),
+    Import(String, String),
+    Struct(StructDecl),
+    Enum(EnumDecl),
+    // `infix 6 <+> = f;`: precedence level, symbol and implementation
+    Infix(u8, String, Expression),
     Return(Expression),
     Expr(Expression),
+    Throw(Expression),
+    Break,
+    Continue,
 }
 
 impl fmt::Display for Statement {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Statement::Let(id, expr) => write!(f, "let {} = {};", id, expr),
+            Statement::Let(pattern, expr) => write!(f, "let {} = {};", pattern, expr),
+            Statement::Export(decl) => write!(f, "export {}", decl),
+            Statement::Import(path, alias) => write!(f, "import \"{}\" as {};", path, alias),
             Statement::Return(expr) => write!(f, "return {};", expr),
             Statement::Expr(expr) => write!(f, "{}", expr),
+            Statement::Struct(decl) => write!(f, "{}", decl),
+            Statement::Enum(decl) => write!(f, "{}", decl),
+            Statement::Infix(level, symbol, expr) => write!(f, "infix {} {} = {};", level, symbol, expr),
+            Statement::Throw(expr) => write!(f, "throw {};", expr),
+            Statement::Break => write!(f, "break;"),
+            Statement::Continue => write!(f, "continue;"),
         }
     }
 }
@@ -52,11 +71,29 @@ pub type BlockStatement = Vec<Statement>;
 pub enum Expression {
     Ident(String),
     Lit(Literal),
+    Template(Vec<TemplateSegment>),
     Prefix(Token, Box<Expression>),
     Infix(Token, Box<Expression>, Box<Expression>),
+    Assign(Token, String, Box<Expression>),
     If(Box<Expression>, BlockStatement, Option<BlockStatement>),
-    Function(Vec<String>, BlockStatement),
+    While(Box<Expression>, BlockStatement),
+    For(Vec<String>, Box<Expression>, BlockStatement),
+    Match(Box<Expression>, Vec<MatchArm>),
+    // try block, optional catch clause with its error binding, optional
+    // finally block
+    Try(BlockStatement, Option<(Option<String>, BlockStatement)>, Option<BlockStatement>),
+    Function(Vec<Parameter>, BlockStatement),
     FunctionCall(Box<Expression>, Vec<Expression>),
+    OptionalCall(Box<Expression>, Vec<Expression>),
+    Array(Vec<Expression>),
+    Hash(Vec<(Expression, Expression)>),
+    Index(Box<Expression>, Box<Expression>),
+    Member(Box<Expression>, String),
+    StructLiteral(Box<Expression>, Vec<(String, Expression)>),
+    Spread(Box<Expression>),
+    // A parenthesized optional chain, which the parentheses end: in
+    // `(f?.())(1)` a null `f` makes the call fail rather than give null
+    Grouped(Box<Expression>),
 }
 
 impl fmt::Display for Expression {
@@ -64,8 +101,23 @@ impl fmt::Display for Expression {
         match self {
             Expression::Ident(id) => write!(f, "{}", id),
             Expression::Lit(lit) => write!(f, "{}", lit),
+            Expression::Template(segments) => {
+                write!(f, "\"")?;
+                for segment in segments {
+                    match segment {
+                        TemplateSegment::Text(text) => write!(f, "{}", text)?,
+                        TemplateSegment::Expr(expr) => write!(f, "${{{}}}", expr)?,
+                    }
+                }
+                write!(f, "\"")
+            }
             Expression::Prefix(op, expr) => write!(f, "({}{})", op, expr),
+            // `is` is a keyword, so print it as written rather than as its token
+            Expression::Infix(Token::Is, left_expr, right_expr) => {
+                write!(f, "({} is {})", left_expr, right_expr)
+            }
             Expression::Infix(op, left_expr, right_expr) => write!(f, "({} {} {})", left_expr, op, right_expr),
+            Expression::Assign(op, id, expr) => write!(f, "({} {} {})", id, op, expr),
             Expression::If(cond, true_block, else_block) => {
                 if let Some(else_block) = else_block {
                     write!(
@@ -79,10 +131,65 @@ impl fmt::Display for Expression {
                     write!(f, "if {} {{ {} }}", cond, format_statements(true_block))
                 }
             },
-            Expression::Function(params, _block) => write!(f, "fn({}) {{...}}", params.join(", "),),
+            Expression::While(cond, body) => {
+                write!(f, "while {} {{ {} }}", cond, format_statements(body))
+            }
+            Expression::For(vars, iterable, body) => write!(
+                f,
+                "for {} in {} {{ {} }}",
+                vars.join(", "),
+                iterable,
+                format_statements(body)
+            ),
+            Expression::Match(subject, arms) => {
+                let arms = arms
+                    .iter()
+                    .map(|arm| arm.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "match {} {{ {} }}", subject, arms)
+            }
+            Expression::Try(body, catch, finally) => {
+                write!(f, "try {{ {} }}", format_statements(body))?;
+                if let Some((param, handler)) = catch {
+                    match param {
+                        Some(param) => write!(f, " catch ({}) {{ {} }}", param, format_statements(handler))?,
+                        None => write!(f, " catch {{ {} }}", format_statements(handler))?,
+                    }
+                }
+                if let Some(finally) = finally {
+                    write!(f, " finally {{ {} }}", format_statements(finally))?;
+                }
+                Ok(())
+            }
+            Expression::Function(params, _block) => write!(f, "fn({}) {{...}}", format_parameters(params, ", ")),
             Expression::FunctionCall(fn_expr, args) => {
                 write!(f, "{}({})", fn_expr, format_expressions(args))
             }
+            Expression::OptionalCall(fn_expr, args) => {
+                write!(f, "{}?.({})", fn_expr, format_expressions(args))
+            }
+            Expression::Array(elements) => write!(f, "[{}]", format_expressions(elements)),
+            Expression::Hash(pairs) => {
+                let pairs = pairs
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key, value))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "{{{}}}", pairs)
+            }
+            Expression::Index(left, index) => write!(f, "({}[{}])", left, index),
+            Expression::Member(object, name) => write!(f, "({}.{})", object, name),
+            Expression::StructLiteral(name, fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|(field, value)| format!("{}: {}", field, value))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "{} {{ {} }}", name, fields)
+            }
+            Expression::Spread(expr) => write!(f, "...{}", expr),
+            Expression::Grouped(expr) => write!(f, "{}", expr),
         }
     }
 }
@@ -95,17 +202,198 @@ fn format_expressions(expressions: &[Expression]) -> String {
         .join(", ")
 }
 
+/// A function parameter: a plain name, a name with a default value that is
+/// evaluated at call time, or a `...rest` parameter collecting any
+/// remaining arguments.
+#[derive(Debug, Clone)]
+pub enum Parameter {
+    Required(String),
+    Optional(String, Expression),
+    Rest(String),
+}
+
+impl Parameter {
+    pub fn name(&self) -> &str {
+        match self {
+            Parameter::Required(name) | Parameter::Optional(name, _) | Parameter::Rest(name) => name,
+        }
+    }
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Parameter::Required(name) => write!(f, "{}", name),
+            Parameter::Optional(name, default) => write!(f, "{} = {}", name, default),
+            Parameter::Rest(name) => write!(f, "...{}", name),
+        }
+    }
+}
+
+pub fn format_parameters(params: &[Parameter], separator: &str) -> String {
+    params
+        .iter()
+        .map(|param| param.to_string())
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+/// A `struct` declaration: the field names, and methods that are called
+/// with the instance bound to `self`.
+#[derive(Debug, Clone)]
+pub struct StructDecl {
+    pub name: String,
+    pub fields: Vec<String>,
+    pub methods: Vec<(String, Vec<Parameter>, BlockStatement)>,
+}
+
+impl fmt::Display for StructDecl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut members = self.fields.clone();
+        for (name, params, _body) in &self.methods {
+            members.push(format!("fn {}({}) {{...}}", name, format_parameters(params, ", ")));
+        }
+        write!(f, "struct {} {{ {} }}", self.name, members.join(", "))
+    }
+}
+
+/// An `enum` declaration: each variant's name and the names of its fields.
+/// A variant without fields is a plain value rather than a constructor.
+#[derive(Debug, Clone)]
+pub struct EnumDecl {
+    pub name: String,
+    pub variants: Vec<(String, Vec<String>)>,
+}
+
+impl fmt::Display for EnumDecl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let variants = self
+            .variants
+            .iter()
+            .map(|(name, fields)| format_variant(name, fields))
+            .collect::<Vec<String>>()
+            .join(", ");
+        write!(f, "enum {} {{ {} }}", self.name, variants)
+    }
+}
+
+/// Formats a variant as `Name(a, b)`, or just `Name` when it has no fields.
+pub fn format_variant<T: fmt::Display>(name: &str, fields: &[T]) -> String {
+    if fields.is_empty() {
+        return name.to_string();
+    }
+    let fields = fields
+        .iter()
+        .map(|field| field.to_string())
+        .collect::<Vec<String>>()
+        .join(", ");
+    format!("{}({})", name, fields)
+}
+
+/// One `pattern if guard => body` arm of a `match` expression.
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expression>,
+    pub body: BlockStatement,
+}
+
+impl fmt::Display for MatchArm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.pattern)?;
+        if let Some(guard) = &self.guard {
+            write!(f, " if {}", guard)?;
+        }
+        write!(f, " => {{ {} }}", format_statements(&self.body))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Wildcard,
+    Binding(String),
+    Lit(Literal),
+    // Leading element patterns, plus an optional `...rest` pattern that
+    // takes the remaining elements
+    Array(Vec<Pattern>, Option<Box<Pattern>>),
+    Hash(Vec<(Literal, Pattern)>),
+    // `Enum.Variant(a, b)`, matched by the enum and variant names
+    Variant(String, String, Vec<Pattern>),
+}
+
+impl Pattern {
+    /// The names this pattern binds, in order.
+    pub fn names(&self) -> Vec<String> {
+        match self {
+            Pattern::Wildcard | Pattern::Lit(_) => Vec::new(),
+            Pattern::Binding(name) => vec![name.clone()],
+            Pattern::Array(elements, rest) => elements
+                .iter()
+                .chain(rest.as_deref())
+                .flat_map(|element| element.names())
+                .collect(),
+            Pattern::Hash(pairs) => pairs.iter().flat_map(|(_, value)| value.names()).collect(),
+            Pattern::Variant(_, _, fields) => fields.iter().flat_map(|field| field.names()).collect(),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Binding(id) => write!(f, "{}", id),
+            Pattern::Lit(lit) => write!(f, "{}", lit),
+            Pattern::Array(elements, rest) => {
+                let mut elements = elements
+                    .iter()
+                    .map(|element| element.to_string())
+                    .collect::<Vec<String>>();
+                if let Some(rest) = rest {
+                    elements.push(format!("...{}", rest));
+                }
+                write!(f, "[{}]", elements.join(", "))
+            }
+            Pattern::Hash(pairs) => {
+                let pairs = pairs
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key, value))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "{{{}}}", pairs)
+            }
+            Pattern::Variant(enum_name, variant, fields) => {
+                write!(f, "{}.{}", enum_name, format_variant(variant, fields))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum TemplateSegment {
+    Text(String),
+    Expr(Expression),
+}
+
 #[derive(Debug, Clone)]
 pub enum Literal {
-    Integer(i32),
+    Integer(i64),
+    BigInt(BigInt),
+    Float(f64),
     Boolean(bool),
+    String(String),
+    Null,
 }
 
 impl fmt::Display for Literal {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Literal::Integer(i) => write!(f, "{}", i),
+            Literal::BigInt(i) => write!(f, "{}", i),
+            Literal::Float(x) => write!(f, "{:?}", x),
             Literal::Boolean(b) => write!(f, "{}", b),
+            Literal::String(s) => write!(f, "\"{}\"", s),
+            Literal::Null => write!(f, "null"),
         }
     }
 }
@@ -0,0 +1,550 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, BitAnd, BitOr, BitXor, Mul, Neg, Not, Shl, Shr, Sub};
+
+/// An arbitrary-precision signed integer.
+///
+/// The magnitude is stored as little-endian base 2^32 limbs with no trailing
+/// zero limbs, so every value has exactly one representation and zero is
+/// never negative.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+impl BigInt {
+    fn new(negative: bool, mut magnitude: Vec<u32>) -> Self {
+        trim(&mut magnitude);
+        let negative = negative && !magnitude.is_empty();
+        BigInt { negative, magnitude }
+    }
+
+    /// Parses a string of digits in the given radix. Returns `None` if the
+    /// string is empty or contains a character that isn't a valid digit.
+    pub fn parse(digits: &str, radix: u32) -> Option<Self> {
+        if digits.is_empty() {
+            return None;
+        }
+
+        let mut magnitude = Vec::new();
+        for c in digits.chars() {
+            let digit = c.to_digit(radix)?;
+            mul_small(&mut magnitude, radix);
+            add_small(&mut magnitude, digit);
+        }
+
+        Some(BigInt::new(false, magnitude))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// Returns the value as an `i64` if it fits.
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None;
+        }
+
+        let magnitude = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0i128, |acc, &limb| (acc << 32) | limb as i128);
+        let value = if self.negative { -magnitude } else { magnitude };
+
+        i64::try_from(value).ok()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0.0, |acc, &limb| acc * 4294967296.0 + limb as f64);
+
+        if self.negative { -magnitude } else { magnitude }
+    }
+
+    /// Divides with truncation toward zero, like Rust's `/` and `%` on
+    /// primitive integers. Returns `None` when dividing by zero.
+    pub fn div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        if other.is_zero() {
+            return None;
+        }
+
+        let (quotient, remainder) = div_rem_magnitude(&self.magnitude, &other.magnitude);
+
+        Some((
+            BigInt::new(self.negative != other.negative, quotient),
+            BigInt::new(self.negative, remainder),
+        ))
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(i: i64) -> Self {
+        let magnitude = i.unsigned_abs();
+        BigInt::new(i < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.magnitude, &other.magnitude),
+            (true, true) => cmp_magnitude(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.magnitude.clone())
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add_magnitude(&self.magnitude, &other.magnitude));
+        }
+
+        match cmp_magnitude(&self.magnitude, &other.magnitude) {
+            Ordering::Less => BigInt::new(other.negative, sub_magnitude(&other.magnitude, &self.magnitude)),
+            _ => BigInt::new(self.negative, sub_magnitude(&self.magnitude, &other.magnitude)),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::new(
+            self.negative != other.negative,
+            mul_magnitude(&self.magnitude, &other.magnitude),
+        )
+    }
+}
+
+impl Not for &BigInt {
+    type Output = BigInt;
+
+    /// Bitwise complement in two's complement, i.e. `-x - 1`.
+    fn not(self) -> BigInt {
+        &-self - &BigInt::from(1)
+    }
+}
+
+impl BitAnd for &BigInt {
+    type Output = BigInt;
+
+    fn bitand(self, other: &BigInt) -> BigInt {
+        bitwise(self, other, |a, b| a & b)
+    }
+}
+
+impl BitOr for &BigInt {
+    type Output = BigInt;
+
+    fn bitor(self, other: &BigInt) -> BigInt {
+        bitwise(self, other, |a, b| a | b)
+    }
+}
+
+impl BitXor for &BigInt {
+    type Output = BigInt;
+
+    fn bitxor(self, other: &BigInt) -> BigInt {
+        bitwise(self, other, |a, b| a ^ b)
+    }
+}
+
+impl Shl<usize> for &BigInt {
+    type Output = BigInt;
+
+    fn shl(self, bits: usize) -> BigInt {
+        let mut magnitude = vec![0u32; bits / 32];
+        let shift = bits % 32;
+        let mut carry = 0u32;
+        for &limb in &self.magnitude {
+            if shift == 0 {
+                magnitude.push(limb);
+            } else {
+                magnitude.push((limb << shift) | carry);
+                carry = limb >> (32 - shift);
+            }
+        }
+        magnitude.push(carry);
+
+        BigInt::new(self.negative, magnitude)
+    }
+}
+
+impl Shr<usize> for &BigInt {
+    type Output = BigInt;
+
+    /// Arithmetic shift, rounding toward negative infinity like `i64 >>`.
+    fn shr(self, bits: usize) -> BigInt {
+        let divisor = &BigInt::from(1) << bits;
+        let (quotient, remainder) = self.div_rem(&divisor).expect("divisor is a power of two");
+
+        if self.negative && !remainder.is_zero() {
+            &quotient - &BigInt::from(1)
+        } else {
+            quotient
+        }
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        // Peel off nine decimal digits at a time, least significant first
+        let mut chunks = Vec::new();
+        let mut magnitude = self.magnitude.clone();
+        while !magnitude.is_empty() {
+            chunks.push(div_rem_small(&mut magnitude, 1_000_000_000));
+        }
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+        let mut chunks = chunks.iter().rev();
+        if let Some(most_significant) = chunks.next() {
+            write!(f, "{}", most_significant)?;
+        }
+        for chunk in chunks {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+/// Applies a limb-wise operation to the two's complement representations of
+/// both operands, sign-extended to a common width.
+fn bitwise(a: &BigInt, b: &BigInt, op: impl Fn(u32, u32) -> u32) -> BigInt {
+    let width = a.magnitude.len().max(b.magnitude.len()) + 1;
+    let a = to_twos_complement(a, width);
+    let b = to_twos_complement(b, width);
+
+    let limbs: Vec<u32> = a.iter().zip(&b).map(|(&x, &y)| op(x, y)).collect();
+    from_twos_complement(limbs)
+}
+
+fn to_twos_complement(i: &BigInt, width: usize) -> Vec<u32> {
+    let mut limbs = i.magnitude.clone();
+    limbs.resize(width, 0);
+
+    if i.negative {
+        for limb in limbs.iter_mut() {
+            *limb = !*limb;
+        }
+        add_small(&mut limbs, 1);
+        limbs.truncate(width);
+    }
+
+    limbs
+}
+
+fn from_twos_complement(mut limbs: Vec<u32>) -> BigInt {
+    let negative = limbs.last().is_some_and(|&limb| limb >> 31 == 1);
+
+    if negative {
+        for limb in limbs.iter_mut() {
+            *limb = !*limb;
+        }
+        add_small(&mut limbs, 1);
+    }
+
+    BigInt::new(negative, limbs)
+}
+
+fn trim(magnitude: &mut Vec<u32>) {
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+}
+
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0u64;
+
+    for i in 0..a.len().max(b.len()) {
+        let sum = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+
+    result
+}
+
+/// Computes `a - b`, which must not be negative.
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+
+    for (i, &limb) in a.iter().enumerate() {
+        let mut diff = limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if diff < 0 {
+            diff += 1 << 32;
+            borrow = 1;
+        }
+        result.push(diff as u32);
+    }
+
+    trim(&mut result);
+    result
+}
+
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = vec![0u32; a.len() + b.len()];
+
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, &y) in b.iter().enumerate() {
+            let product = x as u64 * y as u64 + result[i + j] as u64 + carry;
+            result[i + j] = product as u32;
+            carry = product >> 32;
+        }
+        result[i + b.len()] = carry as u32;
+    }
+
+    trim(&mut result);
+    result
+}
+
+fn mul_small(magnitude: &mut Vec<u32>, m: u32) {
+    let mut carry = 0u64;
+    for limb in magnitude.iter_mut() {
+        let product = *limb as u64 * m as u64 + carry;
+        *limb = product as u32;
+        carry = product >> 32;
+    }
+    if carry > 0 {
+        magnitude.push(carry as u32);
+    }
+}
+
+fn add_small(magnitude: &mut Vec<u32>, n: u32) {
+    let mut carry = n as u64;
+    for limb in magnitude.iter_mut() {
+        if carry == 0 {
+            return;
+        }
+        let sum = *limb as u64 + carry;
+        *limb = sum as u32;
+        carry = sum >> 32;
+    }
+    if carry > 0 {
+        magnitude.push(carry as u32);
+    }
+}
+
+/// Divides `magnitude` in place by `d` and returns the remainder.
+fn div_rem_small(magnitude: &mut Vec<u32>, d: u32) -> u32 {
+    let mut remainder = 0u64;
+    for limb in magnitude.iter_mut().rev() {
+        let current = (remainder << 32) | *limb as u64;
+        *limb = (current / d as u64) as u32;
+        remainder = current % d as u64;
+    }
+    trim(magnitude);
+    remainder as u32
+}
+
+/// Schoolbook binary long division. `b` must be non-zero.
+fn div_rem_magnitude(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_magnitude(a, b) == Ordering::Less {
+        return (Vec::new(), a.to_vec());
+    }
+
+    if b.len() == 1 {
+        let mut quotient = a.to_vec();
+        let remainder = div_rem_small(&mut quotient, b[0]);
+        let mut remainder = vec![remainder];
+        trim(&mut remainder);
+        return (quotient, remainder);
+    }
+
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder: Vec<u32> = Vec::new();
+
+    for bit in (0..a.len() * 32).rev() {
+        // remainder = (remainder << 1) | next bit of a
+        let mut carry = (a[bit / 32] >> (bit % 32)) & 1;
+        for limb in remainder.iter_mut() {
+            let next_carry = *limb >> 31;
+            *limb = (*limb << 1) | carry;
+            carry = next_carry;
+        }
+        if carry > 0 {
+            remainder.push(carry);
+        }
+
+        if cmp_magnitude(&remainder, b) != Ordering::Less {
+            remainder = sub_magnitude(&remainder, b);
+            quotient[bit / 32] |= 1 << (bit % 32);
+        }
+    }
+
+    trim(&mut quotient);
+    (quotient, remainder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(s: &str) -> BigInt {
+        match s.strip_prefix('-') {
+            Some(digits) => -&BigInt::parse(digits, 10).unwrap(),
+            None => BigInt::parse(s, 10).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        let test_case = [
+            "0",
+            "1",
+            "4294967296",
+            "18446744073709551616",
+            "-123456789012345678901234567890",
+            "1000000000000000000000000000001",
+        ];
+
+        for input in test_case {
+            assert_eq!(input, big(input).to_string());
+        }
+
+        assert_eq!("255", BigInt::parse("ff", 16).unwrap().to_string());
+        assert_eq!("10", BigInt::parse("1010", 2).unwrap().to_string());
+        assert_eq!(None, BigInt::parse("12a", 10));
+        assert_eq!(None, BigInt::parse("", 10));
+    }
+
+    #[test]
+    fn test_i64_round_trip() {
+        for i in [0, 1, -1, i64::MAX, i64::MIN, 1 << 32, -(1 << 40)] {
+            assert_eq!(Some(i), BigInt::from(i).to_i64());
+            assert_eq!(i.to_string(), BigInt::from(i).to_string());
+        }
+
+        assert_eq!(None, big("9223372036854775808").to_i64());
+        assert_eq!(Some(i64::MIN), big("-9223372036854775808").to_i64());
+        assert_eq!(None, big("-9223372036854775809").to_i64());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = big("123456789012345678901234567890");
+        let b = big("-987654321098765432109876543210");
+
+        assert_eq!("-864197532086419753208641975320", (&a + &b).to_string());
+        assert_eq!("1111111110111111111011111111100", (&a - &b).to_string());
+        assert_eq!(
+            "-121932631137021795226185032733622923332237463801111263526900",
+            (&a * &b).to_string()
+        );
+        assert_eq!("0", (&a - &a).to_string());
+        assert!(!(&a - &a).is_negative());
+    }
+
+    #[test]
+    fn test_div_rem() {
+        let test_case = [
+            ("100000000000000000000", "3", "33333333333333333333", "1"),
+            ("-100000000000000000000", "3", "-33333333333333333333", "-1"),
+            ("100000000000000000000", "-7", "-14285714285714285714", "2"),
+            ("121932631137021795226185032733622923332237463801111263526900", "123456789012345678901234567890", "987654321098765432109876543210", "0"),
+            ("98765432109876543210", "12345678901234567890", "8", "900000000090"),
+            ("5", "98765432109876543210", "0", "5"),
+        ];
+
+        for (a, b, q, r) in test_case {
+            let (quotient, remainder) = big(a).div_rem(&big(b)).unwrap();
+            assert_eq!(q, quotient.to_string());
+            assert_eq!(r, remainder.to_string());
+        }
+
+        assert_eq!(None, big("1").div_rem(&big("0")));
+    }
+
+    #[test]
+    fn test_bitwise() {
+        let a = big("-18446744073709551617");
+        let b = big("340282366920938463463374607431768211455");
+
+        assert_eq!((&a & &b).to_string(), "340282366920938463444927863358058659839");
+        assert_eq!((&a | &b).to_string(), "-1");
+        assert_eq!((&a ^ &b).to_string(), "-340282366920938463444927863358058659840");
+        assert_eq!((!&a).to_string(), "18446744073709551616");
+
+        for (x, y) in [(12i64, 10i64), (-12, 10), (12, -10), (-12, -10)] {
+            let (bx, by) = (BigInt::from(x), BigInt::from(y));
+            assert_eq!(Some(x & y), (&bx & &by).to_i64());
+            assert_eq!(Some(x | y), (&bx | &by).to_i64());
+            assert_eq!(Some(x ^ y), (&bx ^ &by).to_i64());
+            assert_eq!(Some(!x), (!&bx).to_i64());
+        }
+    }
+
+    #[test]
+    fn test_shifts() {
+        assert_eq!("1267650600228229401496703205376", (&big("1") << 100).to_string());
+        assert_eq!("-36893488147419103232", (&big("-1") << 65).to_string());
+        assert_eq!("1", (&big("1267650600228229401496703205376") >> 100).to_string());
+        assert_eq!("0", (&big("1") >> 1).to_string());
+        assert_eq!("-1", (&big("-1") >> 1).to_string());
+        assert_eq!("-2", (&big("-3") >> 1).to_string());
+        assert_eq!("-1", (&big("-36893488147419103232") >> 200).to_string());
+    }
+
+    #[test]
+    fn test_ordering() {
+        assert!(big("-18446744073709551616") < big("-1"));
+        assert!(big("-1") < big("0"));
+        assert!(big("18446744073709551615") < big("18446744073709551616"));
+        assert_eq!(1.8446744073709552e19, big("18446744073709551616").to_f64());
+    }
+}
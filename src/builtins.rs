@@ -0,0 +1,160 @@
+use crate::evaluator::{ErrorKind, EvalError};
+use crate::object::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+thread_local! {
+    static BUILTINS: RefCell<HashMap<String, Builtin>> = RefCell::new(default_builtins());
+}
+
+/// Looks up a builtin function by name. Identifiers that aren't bound in
+/// the environment fall back to this table.
+pub fn lookup(name: &str) -> Option<Object> {
+    BUILTINS.with(|builtins| builtins.borrow().get(name).cloned().map(Object::Builtin))
+}
+
+/// Registers a builtin function, replacing any existing builtin with the
+/// same name. This lets hosts extend the language without touching the
+/// evaluator.
+pub fn register<F>(name: &str, func: F)
+where
+    F: Fn(&[Object]) -> Result<Object, EvalError> + 'static,
+{
+    BUILTINS.with(|builtins| {
+        builtins
+            .borrow_mut()
+            .insert(name.to_string(), Builtin::new(name, func))
+    });
+}
+
+fn default_builtins() -> HashMap<String, Builtin> {
+    let builtins = [
+        Builtin::new("len", len),
+        Builtin::new("first", first),
+        Builtin::new("last", last),
+        Builtin::new("rest", rest),
+        Builtin::new("push", push),
+        Builtin::new("collect", collect),
+        Builtin::new("error", error),
+        Builtin::new("puts", puts),
+    ];
+
+    builtins
+        .into_iter()
+        .map(|builtin| (builtin.name.clone(), builtin))
+        .collect()
+}
+
+fn check_args(name: &str, args: &[Object], want: usize) -> Result<(), EvalError> {
+    if args.len() != want {
+        return Err(EvalError::with_kind(ErrorKind::ArgumentError, format!(
+            "wrong number of arguments to `{}`: got={}, want={}",
+            name,
+            args.len(),
+            want
+        )));
+    }
+    Ok(())
+}
+
+fn unsupported(name: &str, arg: &Object) -> EvalError {
+    EvalError::with_kind(
+        ErrorKind::TypeError,
+        format!("argument to `{}` not supported, got {}", name, arg),
+    )
+}
+
+fn len(args: &[Object]) -> Result<Object, EvalError> {
+    check_args("len", args, 1)?;
+    let len = match &args[0] {
+        Object::String(s) => s.chars().count(),
+        Object::Array(elements) => elements.len(),
+        Object::Hash(pairs) => pairs.len(),
+        range @ Object::Range { .. } => {
+            return Ok(Object::from_bigint(range.range_len().expect("a range has a length")));
+        }
+        arg => return Err(unsupported("len", arg)),
+    };
+    Ok(Object::Integer(len as i64))
+}
+
+fn first(args: &[Object]) -> Result<Object, EvalError> {
+    check_args("first", args, 1)?;
+    match &args[0] {
+        Object::Array(elements) => Ok(elements.first().cloned().unwrap_or(Object::Null)),
+        arg => Err(unsupported("first", arg)),
+    }
+}
+
+fn last(args: &[Object]) -> Result<Object, EvalError> {
+    check_args("last", args, 1)?;
+    match &args[0] {
+        Object::Array(elements) => Ok(elements.last().cloned().unwrap_or(Object::Null)),
+        arg => Err(unsupported("last", arg)),
+    }
+}
+
+fn rest(args: &[Object]) -> Result<Object, EvalError> {
+    check_args("rest", args, 1)?;
+    match &args[0] {
+        Object::Array(elements) if elements.is_empty() => Ok(Object::Null),
+        Object::Array(elements) => Ok(Object::Array(elements[1..].to_vec())),
+        arg => Err(unsupported("rest", arg)),
+    }
+}
+
+fn push(args: &[Object]) -> Result<Object, EvalError> {
+    check_args("push", args, 2)?;
+    match &args[0] {
+        Object::Array(elements) => {
+            let mut elements = elements.clone();
+            elements.push(args[1].clone());
+            Ok(Object::Array(elements))
+        }
+        arg => Err(unsupported("push", arg)),
+    }
+}
+
+fn collect(args: &[Object]) -> Result<Object, EvalError> {
+    check_args("collect", args, 1)?;
+    args[0].check_collectable()?;
+    match args[0].iter() {
+        Some(items) => Ok(Object::Array(items.collect())),
+        None => Err(unsupported("collect", &args[0])),
+    }
+}
+
+/// `error(message, kind = "Error", data = null)` builds an error value for
+/// `throw`.
+fn error(args: &[Object]) -> Result<Object, EvalError> {
+    if args.is_empty() || args.len() > 3 {
+        return Err(EvalError::with_kind(
+            ErrorKind::ArgumentError,
+            format!("wrong number of arguments to `error`: got={}, want=1 to 3", args.len()),
+        ));
+    }
+
+    let message = match &args[0] {
+        Object::String(message) => message.clone(),
+        arg => return Err(unsupported("error", arg)),
+    };
+    let kind = match args.get(1) {
+        None => ErrorKind::Error,
+        Some(Object::String(kind)) => ErrorKind::from(kind.as_str()),
+        Some(arg) => return Err(unsupported("error", arg)),
+    };
+    let data = match args.get(2) {
+        None | Some(Object::Null) => None,
+        Some(data) => Some(Rc::new(data.clone())),
+    };
+
+    Ok(Object::Error(Box::new(EvalError { kind, message, data })))
+}
+
+fn puts(args: &[Object]) -> Result<Object, EvalError> {
+    for arg in args {
+        println!("{}", arg);
+    }
+    Ok(Object::Null)
+}
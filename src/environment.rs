@@ -1,3 +1,4 @@
+use crate::module::Modules;
 use crate::object::*;
 use std::collections::HashMap;
 use std::rc::Rc;
@@ -10,13 +11,42 @@ pub type Env = Rc<RefCell<Environment>>;
 pub struct Environment {
     store: HashMap<String, Rc<Object>>,
     outer: Option<Env>,
+    modules: Rc<Modules>,
+    // Operators declared with `infix`, by symbol, with their precedence
+    // level. Shared with enclosed environments, and kept from one input to
+    // the next so later ones can use them.
+    operators: Rc<RefCell<HashMap<String, u8>>>,
 }
 
 impl Environment {
+    /// Creates a global environment whose imports go through `modules`.
+    pub fn with_modules(modules: Rc<Modules>) -> Self {
+        Environment {
+            modules,
+            ..Default::default()
+        }
+    }
+
     pub fn new_enclosed_environment(outer: &Env) -> Self {
-        let mut env: Environment = Default::default();
-        env.outer = Some(Rc::clone(outer));
-        env
+        Environment {
+            modules: Rc::clone(&outer.borrow().modules),
+            operators: Rc::clone(&outer.borrow().operators),
+            outer: Some(Rc::clone(outer)),
+            ..Default::default()
+        }
+    }
+
+    pub fn modules(&self) -> &Rc<Modules> {
+        &self.modules
+    }
+
+    /// The operators declared so far, for parsing the next input.
+    pub fn operators(&self) -> HashMap<String, u8> {
+        self.operators.borrow().clone()
+    }
+
+    pub fn declare_operator(&self, symbol: String, level: u8) {
+        self.operators.borrow_mut().insert(symbol, level);
     }
 
     pub fn get(&self, name: &str) -> Option<Rc<Object>> {
@@ -35,4 +65,18 @@ impl Environment {
     pub fn set(&mut self, name: String, val: Rc<Object>) {
         self.store.insert(name, val);
     }
+
+    /// Updates the nearest existing binding of `name`, searching outward
+    /// through enclosing environments. Returns `false` if it isn't bound.
+    pub fn assign(&mut self, name: &str, val: Rc<Object>) -> bool {
+        if let Some(slot) = self.store.get_mut(name) {
+            *slot = val;
+            return true;
+        }
+
+        match &self.outer {
+            Some(outer) => outer.borrow_mut().assign(name, val),
+            None => false,
+        }
+    }
 }
@@ -1,104 +1,263 @@
 use std::fmt;
 use crate::ast::*;
+use crate::bigint::BigInt;
+use crate::builtins;
+use crate::module;
 use crate::object::*;
 use crate::token::*;
 use crate::environment::*;
+use std::collections::BTreeMap;
 use std::rc::Rc;
 use std::cell::RefCell;
 
 
+/// The category of a runtime error. Scripts can inspect it on caught
+/// errors; `Custom` covers kinds created with the `error` builtin.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    Error,
+    TypeError,
+    NameError,
+    ArgumentError,
+    ArithmeticError,
+    MatchError,
+    ImportError,
+    Custom(String),
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::Error => write!(f, "Error"),
+            ErrorKind::TypeError => write!(f, "TypeError"),
+            ErrorKind::NameError => write!(f, "NameError"),
+            ErrorKind::ArgumentError => write!(f, "ArgumentError"),
+            ErrorKind::ArithmeticError => write!(f, "ArithmeticError"),
+            ErrorKind::MatchError => write!(f, "MatchError"),
+            ErrorKind::ImportError => write!(f, "ImportError"),
+            ErrorKind::Custom(kind) => write!(f, "{}", kind),
+        }
+    }
+}
+
+impl From<&str> for ErrorKind {
+    fn from(kind: &str) -> Self {
+        match kind {
+            "Error" => ErrorKind::Error,
+            "TypeError" => ErrorKind::TypeError,
+            "NameError" => ErrorKind::NameError,
+            "ArgumentError" => ErrorKind::ArgumentError,
+            "ArithmeticError" => ErrorKind::ArithmeticError,
+            "MatchError" => ErrorKind::MatchError,
+            "ImportError" => ErrorKind::ImportError,
+            kind => ErrorKind::Custom(kind.to_string()),
+        }
+    }
+}
+
+/// A runtime error. It unwinds evaluation until a `try` catches it, at
+/// which point it becomes an `Object::Error` value.
 #[derive(Debug, Clone)]
-pub struct EvalError(String);
+pub struct EvalError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub data: Option<Rc<Object>>,
+}
 
 impl fmt::Display for EvalError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.0)
+        write!(f, "{}", self.message)
     }
 }
 
 impl EvalError {
     pub fn new(msg: String) -> Self {
-        EvalError(msg)
+        EvalError::with_kind(ErrorKind::Error, msg)
+    }
+
+    pub fn with_kind(kind: ErrorKind, msg: String) -> Self {
+        EvalError {
+            kind,
+            message: msg,
+            data: None,
+        }
     }
 }
 
-fn is_truthy(obj: &Object) -> bool {
-    match *obj {
-        Object::Null => false,
-        Object::Boolean(false) => false,
-        _ => true,
+/// Why evaluation stopped before producing a value. `return`, `break` and
+/// `continue` unwind like errors until the function or loop that handles
+/// them, so no expression can mistake them for a value.
+enum Unwind {
+    Error(EvalError),
+    Return(Object),
+    Break,
+    Continue,
+}
+
+impl From<EvalError> for Unwind {
+    fn from(err: EvalError) -> Self {
+        Unwind::Error(err)
     }
 }
 
+fn is_truthy(obj: &Object) -> bool {
+    !matches!(*obj, Object::Null | Object::Boolean(false))
+}
+
 pub fn eval(node: Node, env: &Env) -> Result<Object, EvalError> {
-    match node {
-        Node::Program(program) => eval_program(&program, env),
+    let result = match node {
+        Node::Program(program) => eval_block_statement(&program, env),
         Node::Stmt(stmt) => eval_statement(&stmt, env),
         Node::Expr(expr) => eval_expression(&expr, env),
-    }
-}
-
-fn eval_program(program: &[Statement], env: &Env) -> Result<Object, EvalError> {
-    let mut result = Object::Null;
+    };
 
-    for stmt in program {
-        let val = eval_statement(stmt, &Rc::clone(env))?;
+    // A top-level `return` ends the program with its value
+    finish_unwind(result)
+}
 
-        match val {
-            Object::ReturnValue(_) => return Ok(val),
-            _ => result = val,
-        }
+/// Ends unwinding at a function or program boundary, where `return` yields its value.
+fn finish_unwind(result: Result<Object, Unwind>) -> Result<Object, EvalError> {
+    match result {
+        Ok(val) | Err(Unwind::Return(val)) => Ok(val),
+        Err(Unwind::Error(err)) => Err(err),
+        // The parser rejects these, but an AST built by hand could still contain them
+        Err(Unwind::Break) => Err(EvalError::new("break outside of a loop".to_string())),
+        Err(Unwind::Continue) => Err(EvalError::new("continue outside of a loop".to_string())),
     }
-
-    Ok(result)
 }
 
-fn eval_block_statement(stmts: &[Statement], env: &Env) -> Result<Object, EvalError> {
+fn eval_block_statement(stmts: &[Statement], env: &Env) -> Result<Object, Unwind> {
     let mut result = Object::Null;
 
     for stmt in stmts {
-        let val = eval_statement(stmt, &Rc::clone(env))?;
-
-        match val {
-            Object::ReturnValue(_) => return Ok(val),
-            _ => result = val,
-        }
+        result = eval_statement(stmt, &Rc::clone(env))?;
     }
 
     Ok(result)
 }
 
-fn eval_statement(stmt: &Statement, env: &Env) -> Result<Object, EvalError> {
+fn eval_statement(stmt: &Statement, env: &Env) -> Result<Object, Unwind> {
     match stmt {
-        Statement::Let(id, expr) => {
+        Statement::Export(decl) => eval_statement(decl, env),
+        Statement::Let(pattern, expr) => {
             let val = eval_expression(expr, &Rc::clone(env))?;
-            let obj = Rc::new(val.clone());
-            env.borrow_mut().set(id.clone(), obj);
+
+            let mut bindings = Vec::new();
+            match_pattern(pattern, &val, env, &mut bindings).map_err(|reason| match reason {
+                Mismatch::Invalid(err) => err,
+                reason => EvalError::with_kind(
+                    ErrorKind::MatchError,
+                    format!("cannot destructure {}: {}", val, reason),
+                ),
+            })?;
+            for (name, value) in bindings {
+                env.borrow_mut().set(name, Rc::new(value));
+            }
+
             Ok(val)
         }
         Statement::Expr(expr) => eval_expression(expr, env),
         Statement::Return(expr) => {
+            Err(Unwind::Return(eval_expression(expr, env)?))
+        }
+        Statement::Import(path, alias) => {
+            let module = module::import(path, env)?;
+            env.borrow_mut().set(alias.clone(), Rc::new(module.clone()));
+            Ok(module)
+        }
+        Statement::Throw(expr) => {
             let val = eval_expression(expr, env)?;
-            Ok(Object::ReturnValue(val.into()))
+            Err(Unwind::Error(match val {
+                Object::Error(err) => *err,
+                // Any other value becomes the data of a generic error
+                val => EvalError {
+                    kind: ErrorKind::Error,
+                    message: val.to_string(),
+                    data: Some(Rc::new(val)),
+                },
+            }))
         }
-
+        Statement::Struct(decl) => {
+            let methods = decl
+                .methods
+                .iter()
+                .map(|(name, params, body)| (name.clone(), (params.clone(), body.clone())))
+                .collect();
+            let ty = Object::StructType(Rc::new(StructType {
+                name: decl.name.clone(),
+                fields: decl.fields.clone(),
+                methods,
+                env: Rc::clone(env),
+            }));
+            env.borrow_mut().set(decl.name.clone(), Rc::new(ty.clone()));
+            Ok(ty)
+        }
+        Statement::Enum(decl) => {
+            let ty = Object::EnumType(Rc::new(EnumType {
+                name: decl.name.clone(),
+                variants: decl.variants.clone(),
+            }));
+            env.borrow_mut().set(decl.name.clone(), Rc::new(ty.clone()));
+            Ok(ty)
+        }
+        // The implementation is bound under the symbol itself, which no
+        // identifier can shadow. Only now does the operator exist for later
+        // inputs, so a declaration that never runs leaves nothing behind.
+        Statement::Infix(level, symbol, expr) => {
+            let val = eval_expression(expr, env)?;
+            check_operator_function(symbol, &val)?;
+            env.borrow_mut().set(symbol.clone(), Rc::new(val.clone()));
+            env.borrow().declare_operator(symbol.clone(), *level);
+            Ok(val)
+        }
+        Statement::Break => Err(Unwind::Break),
+        Statement::Continue => Err(Unwind::Continue),
     }
 }
 
 
-fn eval_expression(expr: &Expression, env: &Env) -> Result<Object, EvalError> {
+fn eval_expression(expr: &Expression, env: &Env) -> Result<Object, Unwind> {
     match expr {
-        Expression::Ident(id) => eval_identifier(&id, env),
-        Expression::Lit(lit) => eval_literal(lit),
+        Expression::Ident(id) => Ok(eval_identifier(id, env)?),
+        Expression::Lit(lit) => Ok(eval_literal(lit)?),
+        Expression::Template(segments) => eval_template(segments, env),
         Expression::Prefix(op, expr) => {
             let right = eval_expression(expr, env)?;
-            eval_prefix_expression(op, &right)
+            Ok(eval_prefix_expression(op, &right)?)
+        },
+        Expression::Infix(op @ (Token::And | Token::Or), left, right) => {
+            eval_logical_expression(op, left, right, env)
+        },
+        Expression::Infix(Token::NullCoalesce, left, right) => {
+            match eval_expression(left, env)? {
+                Object::Null => eval_expression(right, env),
+                left => Ok(left),
+            }
+        },
+        Expression::Infix(Token::Is, left, right) => {
+            let left = eval_expression(left, env)?;
+            let right = eval_expression(right, env)?;
+            Ok(eval_is_expression(&left, &right)?)
+        },
+        Expression::Infix(Token::Operator(symbol), left, right) => {
+            let func = env.borrow().get(symbol).ok_or_else(|| {
+                EvalError::with_kind(ErrorKind::NameError, format!("operator not defined: {}", symbol))
+            })?;
+            let left = eval_expression(left, env)?;
+            let right = eval_expression(right, env)?;
+            Ok(apply_function(&func, &[left, right])?)
+        },
+        Expression::Infix(op @ (Token::DotDot | Token::DotDotEq), left, right) => {
+            let left = eval_expression(left, env)?;
+            let right = eval_expression(right, env)?;
+            Ok(eval_range_expression(op, &left, &right)?)
         },
         Expression::Infix(op, left, right) => {
             let left = eval_expression(left, &Rc::clone(env))?;
             let right = eval_expression(right, &Rc::clone(env))?;
-            eval_infix_expression(op, &left, &right)
+            Ok(eval_infix_expression(op, &left, &right)?)
         },
+        Expression::Assign(op, id, value) => eval_assign_expression(op, id, value, env),
         Expression::If(condition, consequence, alternative) => {
             let condition = eval_expression(condition, &Rc::clone(env))?;
 
@@ -111,28 +270,318 @@ fn eval_expression(expr: &Expression, env: &Env) -> Result<Object, EvalError> {
                 }
             }
         },
+        Expression::While(condition, body) => eval_while_expression(condition, body, env),
+        Expression::For(vars, iterable, body) => eval_for_expression(vars, iterable, body, env),
+        Expression::Match(subject, arms) => eval_match_expression(subject, arms, env),
+        Expression::Try(body, catch, finally) => eval_try_expression(body, catch, finally, env),
+        Expression::Member(..)
+        | Expression::FunctionCall(..)
+        | Expression::OptionalCall(..)
+        | Expression::Index(..) => Ok(eval_chain(expr, env)?.unwrap_or(Object::Null)),
+        Expression::StructLiteral(ty, fields) => eval_struct_literal(ty, fields, env),
+        Expression::Grouped(expr) => eval_expression(expr, env),
+        Expression::Spread(expr) => Err(EvalError::with_kind(ErrorKind::TypeError, format!(
+            "spread is only allowed in argument and array lists: ...{}",
+            expr
+        )).into()),
         Expression::Function(params, body) => Ok(Object::Function(
             params.clone(),
             body.clone(),
-            Rc::clone(&env),
+            Rc::clone(env),
         )),
+        Expression::Array(elements) => Ok(Object::Array(eval_expressions(elements, env)?)),
+        Expression::Hash(pairs) => eval_hash_literal(pairs, env),
+    }
+}
+
+/// Evaluates a chain of calls, indexes and member accesses. `None` means an
+/// optional call on `null` cut the chain short, making all of it `null`.
+fn eval_chain(expr: &Expression, env: &Env) -> Result<Option<Object>, Unwind> {
+    let value = match expr {
         Expression::FunctionCall(func, args) => {
-            let func = eval_expression(func, &Rc::clone(env))?;
-            let args: Result<Vec<Object>, EvalError> = args.iter().map(|arg| eval_expression(arg, env)).collect();
-            apply_function(&func, &args?)
+            let Some(func) = eval_chain(func, env)? else {
+                return Ok(None);
+            };
+            let args = eval_expressions(args, env)?;
+            apply_function(&func, &args)?
         }
-        _ => Err(EvalError::new(format!(
-            "unknown expression: {}",
-            expr
+        Expression::OptionalCall(func, args) => match eval_chain(func, env)? {
+            None | Some(Object::Null) => return Ok(None),
+            Some(func) => {
+                let args = eval_expressions(args, env)?;
+                apply_function(&func, &args)?
+            }
+        },
+        Expression::Index(left, index) => {
+            let Some(left) = eval_chain(left, env)? else {
+                return Ok(None);
+            };
+            let index = eval_expression(index, env)?;
+            eval_index_expression(&left, &index)?
+        }
+        Expression::Member(object, name) => {
+            let Some(object) = eval_chain(object, env)? else {
+                return Ok(None);
+            };
+            eval_member_expression(&object, name)?
+        }
+        _ => eval_expression(expr, env)?,
+    };
+    Ok(Some(value))
+}
+
+fn eval_hash_literal(pairs: &[(Expression, Expression)], env: &Env) -> Result<Object, Unwind> {
+    let mut hash = BTreeMap::new();
+
+    for (key, value) in pairs {
+        let key = eval_expression(key, env)?;
+        let key = hash_key(&key)?;
+        let value = eval_expression(value, env)?;
+        hash.insert(key, value);
+    }
+
+    Ok(Object::Hash(hash))
+}
+
+fn hash_key(obj: &Object) -> Result<HashKey, EvalError> {
+    obj.hash_key()
+        .ok_or_else(|| {
+            EvalError::with_kind(ErrorKind::TypeError, format!("unusable as hash key: {}", obj))
+        })
+}
+
+/// Reads `message`, `kind` or `data` from a caught error.
+fn error_field(err: &EvalError, name: &str) -> Option<Object> {
+    match name {
+        "message" => Some(Object::String(err.message.clone())),
+        "kind" => Some(Object::String(err.kind.to_string())),
+        "data" => Some(err.data.as_deref().cloned().unwrap_or(Object::Null)),
+        _ => None,
+    }
+}
+
+fn eval_index_expression(left: &Object, index: &Object) -> Result<Object, EvalError> {
+    match (left, index) {
+        (Object::Array(elements), Object::Integer(i)) => {
+            let element = usize::try_from(*i).ok().and_then(|i| elements.get(i));
+            Ok(element.cloned().unwrap_or(Object::Null))
+        }
+        (Object::Array(_), Object::BigInt(_)) => Ok(Object::Null),
+        (Object::Error(err), Object::String(field)) => Ok(error_field(err, field).unwrap_or(Object::Null)),
+        (Object::Hash(pairs), key) => {
+            let key = hash_key(key)?;
+            Ok(pairs.get(&key).cloned().unwrap_or(Object::Null))
+        }
+        _ => Err(EvalError::with_kind(ErrorKind::TypeError, format!(
+            "index operator not supported: {}[{}]",
+            left, index
         ))),
     }
 }
 
+fn eval_member_expression(object: &Object, name: &str) -> Result<Object, EvalError> {
+    match object {
+        Object::Module(module) => module.exports.get(name).cloned().ok_or_else(|| {
+            EvalError::with_kind(
+                ErrorKind::NameError,
+                format!("module {} has no export named {}", module.path, name),
+            )
+        }),
+        Object::Struct(ty, values) => {
+            if let Some(i) = ty.fields.iter().position(|field| field == name) {
+                return Ok(values[i].clone());
+            }
+
+            // A method comes back bound to its instance as `self`
+            let (params, body) = ty.methods.get(name).ok_or_else(|| {
+                EvalError::with_kind(
+                    ErrorKind::NameError,
+                    format!("{} has no field or method named {}", ty.name, name),
+                )
+            })?;
+            let mut scope = Environment::new_enclosed_environment(&ty.env);
+            scope.set("self".to_string(), Rc::new(object.clone()));
+            Ok(Object::Function(params.clone(), body.clone(), Rc::new(RefCell::new(scope))))
+        }
+        Object::EnumType(ty) => {
+            let index = ty.variants.iter().position(|(variant, _)| variant == name).ok_or_else(|| {
+                EvalError::with_kind(
+                    ErrorKind::NameError,
+                    format!("enum {} has no variant named {}", ty.name, name),
+                )
+            })?;
+            // A variant without fields is a value rather than a constructor
+            if ty.variants[index].1.is_empty() {
+                Ok(Object::Variant(Rc::clone(ty), index, Vec::new()))
+            } else {
+                Ok(Object::Constructor(Rc::clone(ty), index))
+            }
+        }
+        Object::Variant(ty, index, values) => {
+            let (variant, fields) = &ty.variants[*index];
+            let i = fields.iter().position(|field| field == name).ok_or_else(|| {
+                EvalError::with_kind(
+                    ErrorKind::NameError,
+                    format!("{}.{} has no field named {}", ty.name, variant, name),
+                )
+            })?;
+            Ok(values[i].clone())
+        }
+        Object::Error(err) => error_field(err, name).ok_or_else(|| {
+            EvalError::with_kind(ErrorKind::NameError, format!("error has no field named {}", name))
+        }),
+        _ => Err(EvalError::with_kind(
+            ErrorKind::TypeError,
+            format!("cannot access field {} on {}", name, object),
+        )),
+    }
+}
+
+/// `value is T` tests whether a value was built by a variant constructor or
+/// unit variant, or belongs to an enum or struct type.
+fn eval_is_expression(value: &Object, ty: &Object) -> Result<Object, EvalError> {
+    let is = match (value, ty) {
+        (
+            Object::Variant(value_ty, value_index, _),
+            Object::Constructor(ty, index) | Object::Variant(ty, index, _),
+        ) => {
+            Rc::ptr_eq(value_ty, ty) && value_index == index
+        }
+        (_, Object::Constructor(..) | Object::Variant(..)) => false,
+        (Object::Variant(value_ty, _, _), Object::EnumType(ty)) => Rc::ptr_eq(value_ty, ty),
+        (_, Object::EnumType(_)) => false,
+        (Object::Struct(value_ty, _), Object::StructType(ty)) => Rc::ptr_eq(value_ty, ty),
+        (_, Object::StructType(_)) => false,
+        _ => {
+            return Err(EvalError::with_kind(
+                ErrorKind::TypeError,
+                format!("expected a type or variant after is, got {}", ty),
+            ))
+        }
+    };
+    Ok(Object::Boolean(is))
+}
+
+fn eval_struct_literal(
+    name: &Expression,
+    fields: &[(String, Expression)],
+    env: &Env,
+) -> Result<Object, Unwind> {
+    let ty = match eval_expression(name, env)? {
+        Object::StructType(ty) => ty,
+        _ => {
+            return Err(EvalError::with_kind(
+                ErrorKind::TypeError,
+                format!("{} is not a struct", name),
+            ).into())
+        }
+    };
+
+    let mut values = vec![None; ty.fields.len()];
+    for (field, expr) in fields {
+        let i = ty.fields.iter().position(|f| f == field).ok_or_else(|| {
+            EvalError::with_kind(
+                ErrorKind::NameError,
+                format!("{} has no field or method named {}", ty.name, field),
+            )
+        })?;
+        if values[i].is_some() {
+            return Err(EvalError::with_kind(
+                ErrorKind::ArgumentError,
+                format!("duplicate field {} for {}", field, ty.name),
+            ).into());
+        }
+        values[i] = Some(eval_expression(expr, env)?);
+    }
+
+    let values = values
+        .into_iter()
+        .zip(&ty.fields)
+        .map(|(value, field)| {
+            value.ok_or_else(|| {
+                EvalError::with_kind(
+                    ErrorKind::ArgumentError,
+                    format!("missing field {} for {}", field, ty.name),
+                )
+            })
+        })
+        .collect::<Result<Vec<Object>, EvalError>>()?;
+
+    Ok(Object::Struct(ty, values))
+}
+
+fn eval_assign_expression(op: &Token, id: &str, value: &Expression, env: &Env) -> Result<Object, Unwind> {
+    let mut val = eval_expression(value, env)?;
+
+    if let Some(infix_op) = op.compound_operator() {
+        let current = env.borrow().get(id);
+        match current {
+            Some(current) => val = eval_infix_expression(&infix_op, &current, &val)?,
+            None => {
+                return Err(EvalError::with_kind(
+                    ErrorKind::NameError,
+                    format!("identifier not found: {}", id),
+                ).into())
+            }
+        }
+    }
+
+    if env.borrow_mut().assign(id, Rc::new(val.clone())) {
+        Ok(val)
+    } else {
+        Err(EvalError::with_kind(
+            ErrorKind::NameError,
+            format!("cannot assign to unbound identifier: {}", id),
+        ).into())
+    }
+}
+
+/// Evaluates an argument or array list, expanding `...spread` elements.
+fn eval_expressions(exprs: &[Expression], env: &Env) -> Result<Vec<Object>, Unwind> {
+    let mut values = Vec::new();
+
+    for expr in exprs {
+        match expr {
+            Expression::Spread(expr) => {
+                let value = eval_expression(expr, env)?;
+                value.check_collectable()?;
+                match value.iter() {
+                    Some(items) => values.extend(items),
+                    None => {
+                        return Err(EvalError::with_kind(
+                            ErrorKind::TypeError,
+                            format!("cannot spread {}", value),
+                        ).into())
+                    }
+                }
+            }
+            expr => values.push(eval_expression(expr, env)?),
+        }
+    }
+
+    Ok(values)
+}
+
+/// Evaluates `&&` and `||`, only evaluating the right operand when the left
+/// one doesn't already decide the result.
+fn eval_logical_expression(op: &Token, left: &Expression, right: &Expression, env: &Env) -> Result<Object, Unwind> {
+    let left = is_truthy(&eval_expression(left, env)?);
+
+    let result = match op {
+        Token::And if !left => false,
+        Token::Or if left => true,
+        _ => is_truthy(&eval_expression(right, env)?),
+    };
+
+    Ok(Object::Boolean(result))
+}
+
 fn eval_prefix_expression(op: &Token, expr: &Object) -> Result<Object, EvalError> {
     match op {
         Token::Bang => eval_bang_operator(expr),
         Token::Dash => eval_minus_prefix_operator(expr),
-        _ => Err(EvalError::new(format!(
+        Token::Tilde => eval_bitwise_not_operator(expr),
+        _ => Err(EvalError::with_kind(ErrorKind::TypeError, format!(
             "unknown operator: {}{}",
             op, expr
         ))),
@@ -140,33 +589,214 @@ fn eval_prefix_expression(op: &Token, expr: &Object) -> Result<Object, EvalError
 }
 
 fn eval_infix_expression(op: &Token, left: &Object, right: &Object) -> Result<Object, EvalError> {
-    match (&*left, &*right) {
+    match (left, right) {
         (Object::Integer(left_val), Object::Integer(right_val)) => {
             eval_integer_infix_expression(op, *left_val, *right_val)
         }
+        (Object::BigInt(left_val), Object::BigInt(right_val)) => {
+            eval_bigint_infix_expression(op, left_val, right_val)
+        }
+        (Object::BigInt(left_val), Object::Integer(right_val)) => {
+            eval_bigint_infix_expression(op, left_val, &BigInt::from(*right_val))
+        }
+        (Object::Integer(left_val), Object::BigInt(right_val)) => {
+            eval_bigint_infix_expression(op, &BigInt::from(*left_val), right_val)
+        }
+        (Object::Float(left_val), Object::Float(right_val)) => {
+            eval_float_infix_expression(op, *left_val, *right_val)
+        }
+        (Object::Integer(left_val), Object::Float(right_val)) => {
+            eval_float_infix_expression(op, *left_val as f64, *right_val)
+        }
+        (Object::Float(left_val), Object::Integer(right_val)) => {
+            eval_float_infix_expression(op, *left_val, *right_val as f64)
+        }
+        (Object::BigInt(left_val), Object::Float(right_val)) => {
+            eval_float_infix_expression(op, left_val.to_f64(), *right_val)
+        }
+        (Object::Float(left_val), Object::BigInt(right_val)) => {
+            eval_float_infix_expression(op, *left_val, right_val.to_f64())
+        }
         (Object::Boolean(left_val), Object::Boolean(right_val)) => {
             eval_boolean_infix_expression(op, *left_val, *right_val)
         }
-        _ => Err(EvalError::new(format!(
+        (Object::String(left_val), Object::String(right_val)) => {
+            eval_string_infix_expression(op, left_val, right_val)
+        }
+        (Object::Struct(..) | Object::Variant(..), Object::Struct(..) | Object::Variant(..))
+            if matches!(op, Token::Equal | Token::NotEqual) =>
+        {
+            let equal = instances_equal(left, right);
+            Ok(Object::Boolean(equal == (*op == Token::Equal)))
+        }
+        // `null` can be compared with anything, and equals only itself
+        (Object::Null, _) | (_, Object::Null) if matches!(op, Token::Equal | Token::NotEqual) => {
+            let equal = matches!((left, right), (Object::Null, Object::Null));
+            Ok(Object::Boolean(equal == (*op == Token::Equal)))
+        }
+        _ => Err(EvalError::with_kind(ErrorKind::TypeError, format!(
             "type mismatch: {} {} {}",
             left, op, right
         ))),
     }
 }
 
-fn eval_integer_infix_expression(op: &Token, left_val: i32, right_val: i32) -> Result<Object, EvalError> {
+/// Struct instances and enum variants are equal when they come from the
+/// same declaration and their fields are `==`. Fields that `==` can't
+/// compare, such as arrays or functions, make the instances unequal.
+fn instances_equal(left: &Object, right: &Object) -> bool {
+    let (left_values, right_values) = match (left, right) {
+        (Object::Struct(left_ty, left_values), Object::Struct(right_ty, right_values))
+            if Rc::ptr_eq(left_ty, right_ty) =>
+        {
+            (left_values, right_values)
+        }
+        (
+            Object::Variant(left_ty, left_index, left_values),
+            Object::Variant(right_ty, right_index, right_values),
+        ) if Rc::ptr_eq(left_ty, right_ty) && left_index == right_index => {
+            (left_values, right_values)
+        }
+        _ => return false,
+    };
+
+    left_values.iter().zip(right_values).all(|(left, right)| {
+        matches!(eval_infix_expression(&Token::Equal, left, right), Ok(Object::Boolean(true)))
+    })
+}
+
+fn eval_integer_infix_expression(op: &Token, left_val: i64, right_val: i64) -> Result<Object, EvalError> {
+    let result = match op {
+        Token::Plus => left_val.checked_add(right_val),
+        Token::Dash => left_val.checked_sub(right_val),
+        Token::Asterisk => left_val.checked_mul(right_val),
+        Token::ForwardSlash => {
+            if right_val == 0 {
+                return Err(division_by_zero(op, left_val, right_val));
+            }
+            left_val.checked_div(right_val)
+        }
+        Token::Percent => {
+            if right_val == 0 {
+                return Err(division_by_zero(op, left_val, right_val));
+            }
+            left_val.checked_rem(right_val)
+        }
+        Token::Ampersand => Some(left_val & right_val),
+        Token::Pipe => Some(left_val | right_val),
+        Token::Caret => Some(left_val ^ right_val),
+        Token::ShiftLeft => {
+            let shift = shift_amount(&BigInt::from(right_val))?;
+            // Fast path only when no bits are shifted out
+            match left_val.checked_shl(shift as u32) {
+                Some(i) if shift < 64 && i >> shift == left_val => Some(i),
+                _ if left_val == 0 => Some(0),
+                _ => None,
+            }
+        }
+        Token::ShiftRight => {
+            let shift = shift_amount(&BigInt::from(right_val))?;
+            Some(left_val >> shift.min(63))
+        }
+        Token::LessThan => return Ok(Object::Boolean(left_val < right_val)),
+        Token::GreaterThan => return Ok(Object::Boolean(left_val > right_val)),
+        Token::LessEqual => return Ok(Object::Boolean(left_val <= right_val)),
+        Token::GreaterEqual => return Ok(Object::Boolean(left_val >= right_val)),
+        Token::Equal => return Ok(Object::Boolean(left_val == right_val)),
+        Token::NotEqual => return Ok(Object::Boolean(left_val != right_val)),
+        op => {
+            return Err(EvalError::with_kind(ErrorKind::TypeError, format!(
+                "unknown operator: {} {} {}",
+                left_val, op, right_val
+            )))
+        }
+    };
+
+    match result {
+        Some(i) => Ok(Object::Integer(i)),
+        // Overflowed the fast path, so redo the operation with big integers
+        None => eval_bigint_infix_expression(op, &BigInt::from(left_val), &BigInt::from(right_val)),
+    }
+}
+
+fn eval_bigint_infix_expression(op: &Token, left_val: &BigInt, right_val: &BigInt) -> Result<Object, EvalError> {
+    let result = match op {
+        Token::Plus => left_val + right_val,
+        Token::Dash => left_val - right_val,
+        Token::Asterisk => left_val * right_val,
+        Token::ForwardSlash => match left_val.div_rem(right_val) {
+            Some((quotient, _)) => quotient,
+            None => return Err(division_by_zero(op, left_val, right_val)),
+        },
+        Token::Percent => match left_val.div_rem(right_val) {
+            Some((_, remainder)) => remainder,
+            None => return Err(division_by_zero(op, left_val, right_val)),
+        },
+        Token::Ampersand => left_val & right_val,
+        Token::Pipe => left_val | right_val,
+        Token::Caret => left_val ^ right_val,
+        Token::ShiftLeft => left_val << shift_amount(right_val)?,
+        Token::ShiftRight => left_val >> shift_amount(right_val)?,
+        Token::LessThan => return Ok(Object::Boolean(left_val < right_val)),
+        Token::GreaterThan => return Ok(Object::Boolean(left_val > right_val)),
+        Token::LessEqual => return Ok(Object::Boolean(left_val <= right_val)),
+        Token::GreaterEqual => return Ok(Object::Boolean(left_val >= right_val)),
+        Token::Equal => return Ok(Object::Boolean(left_val == right_val)),
+        Token::NotEqual => return Ok(Object::Boolean(left_val != right_val)),
+        op => {
+            return Err(EvalError::with_kind(ErrorKind::TypeError, format!(
+                "unknown operator: {} {} {}",
+                left_val, op, right_val
+            )))
+        }
+    };
+
+    Ok(Object::from_bigint(result))
+}
+
+/// Shifting further than this would allocate an unreasonably large integer.
+const MAX_SHIFT: usize = 1 << 20;
+
+fn shift_amount(shift: &BigInt) -> Result<usize, EvalError> {
+    if shift.is_negative() {
+        return Err(EvalError::with_kind(
+            ErrorKind::ArithmeticError,
+            format!("negative shift amount: {}", shift),
+        ));
+    }
+
+    match shift.to_i64() {
+        Some(i) if i as usize <= MAX_SHIFT => Ok(i as usize),
+        _ => Err(EvalError::with_kind(
+            ErrorKind::ArithmeticError,
+            format!("shift amount too large: {}", shift),
+        )),
+    }
+}
+
+fn division_by_zero(op: &Token, left_val: impl fmt::Display, right_val: impl fmt::Display) -> EvalError {
+    EvalError::with_kind(ErrorKind::ArithmeticError, format!(
+        "division by zero: {} {} {}",
+        left_val, op, right_val
+    ))
+}
+
+fn eval_float_infix_expression(op: &Token, left_val: f64, right_val: f64) -> Result<Object, EvalError> {
     let result = match op {
-        Token::Plus => Object::Integer(left_val + right_val),
-        Token::Dash => Object::Integer(left_val - right_val),
-        Token::Asterisk => Object::Integer(left_val * right_val),
-        Token::ForwardSlash => Object::Integer(left_val / right_val),
+        Token::Plus => Object::Float(left_val + right_val),
+        Token::Dash => Object::Float(left_val - right_val),
+        Token::Asterisk => Object::Float(left_val * right_val),
+        Token::ForwardSlash => Object::Float(left_val / right_val),
+        Token::Percent => Object::Float(left_val % right_val),
         Token::LessThan => Object::Boolean(left_val < right_val),
         Token::GreaterThan => Object::Boolean(left_val > right_val),
+        Token::LessEqual => Object::Boolean(left_val <= right_val),
+        Token::GreaterEqual => Object::Boolean(left_val >= right_val),
         Token::Equal => Object::Boolean(left_val == right_val),
         Token::NotEqual => Object::Boolean(left_val != right_val),
         op => {
-            return Err(EvalError::new(format!(
-                "unknown operator: {} {} {}",
+            return Err(EvalError::with_kind(ErrorKind::TypeError, format!(
+                "unknown operator: {:?} {} {:?}",
                 left_val, op, right_val
             )))
         }
@@ -180,7 +810,7 @@ fn eval_boolean_infix_expression(op: &Token, left_val: bool, right_val: bool) ->
         Token::Equal => Object::Boolean(left_val == right_val),
         Token::NotEqual => Object::Boolean(left_val != right_val),
         op => {
-            return Err(EvalError::new(format!(
+            return Err(EvalError::with_kind(ErrorKind::TypeError, format!(
                 "unknown operator: {} {} {}",
                 left_val, op, right_val
             )))
@@ -190,95 +820,472 @@ fn eval_boolean_infix_expression(op: &Token, left_val: bool, right_val: bool) ->
     Ok(result)
 }
 
-fn apply_function(function: &Object, args: &[Object]) -> Result<Object, EvalError> {
-    match function {
-        Object::Function(params, body, env) => {
-            let mut env = Environment::new_enclosed_environment(env);
-
-            if params.len() != args.len() {
-                return Err(EvalError::new(format!(
-                    "invalid number of arguments: exected={}, got={}",
-                    params.len(),
-                    args.len()
-                )));
-            }
+fn eval_string_infix_expression(op: &Token, left_val: &str, right_val: &str) -> Result<Object, EvalError> {
+    let result = match op {
+        Token::Plus => Object::String(format!("{}{}", left_val, right_val)),
+        Token::Equal => Object::Boolean(left_val == right_val),
+        Token::NotEqual => Object::Boolean(left_val != right_val),
+        op => {
+            return Err(EvalError::with_kind(ErrorKind::TypeError, format!(
+                "unknown operator: {} {} {}",
+                left_val, op, right_val
+            )))
+        }
+    };
 
-            params.iter().enumerate().for_each(|(i, param)| {
-                env.set(param.clone(), Rc::new(args[i].clone()));
-            });
+    Ok(result)
+}
 
-            let evaluated = eval_block_statement(&body, &Rc::new(RefCell::new(env)))?;
-            unwrap_return_value(Rc::new(evaluated))
+fn eval_while_expression(
+    condition: &Expression,
+    body: &BlockStatement,
+    env: &Env,
+) -> Result<Object, Unwind> {
+    while is_truthy(&eval_expression(condition, env)?) {
+        match eval_block_statement(body, env) {
+            Err(Unwind::Break) => break,
+            Ok(_) | Err(Unwind::Continue) => {}
+            Err(unwind) => return Err(unwind),
         }
-        f => Err(EvalError::new(format!("not a function: {}", f))),
     }
+
+    Ok(Object::Null)
 }
 
-fn unwrap_return_value(obj: Rc<Object>) -> Result<Object, EvalError> {
-    if let Object::ReturnValue(val) = &*obj {
-        Ok((**val).clone())
+fn eval_for_expression(
+    vars: &[String],
+    iterable: &Expression,
+    body: &BlockStatement,
+    env: &Env,
+) -> Result<Object, Unwind> {
+    let iterable = eval_expression(iterable, env)?;
+    let not_iterable = || EvalError::with_kind(ErrorKind::TypeError, format!("not iterable: {}", iterable));
+
+    let items: Box<dyn Iterator<Item = Vec<Object>>> = if vars.len() == 1 {
+        Box::new(iterable.iter().ok_or_else(not_iterable)?.map(|item| vec![item]))
     } else {
-        Ok((*obj).clone())
-    }
-}
+        Box::new(iterable.iter_pairs().ok_or_else(not_iterable)?.map(|(k, v)| vec![k, v]))
+    };
 
-fn eval_bang_operator(expr: &Object) -> Result<Object, EvalError> {
-    match *expr {
-        Object::Boolean(b) => Ok(Object::Boolean(!b)),
-        Object::Null => Ok(Object::Boolean(true)),
-        _ => Ok(Object::Boolean(false)),
-    }
-}
+    for values in items {
+        // Each iteration gets its own scope so closures capture that
+        // iteration's values
+        let mut scope = Environment::new_enclosed_environment(env);
+        for (var, value) in vars.iter().zip(values) {
+            scope.set(var.clone(), Rc::new(value));
+        }
 
-fn eval_minus_prefix_operator(expr: &Object) -> Result<Object, EvalError> {
-    match *expr {
-        Object::Integer(b) => Ok(Object::Integer(-b)),
-        _ => Err(EvalError::new(format!(
-            "unknown operator: -{}",
-            expr
-        ))),
+        match eval_block_statement(body, &Rc::new(RefCell::new(scope))) {
+            Err(Unwind::Break) => break,
+            Ok(_) | Err(Unwind::Continue) => {}
+            Err(unwind) => return Err(unwind),
+        }
     }
+
+    Ok(Object::Null)
 }
 
-fn eval_identifier(id: &str, env: &Env) -> Result<Object, EvalError> {
-    match env.borrow().get(id) {
-        Some(obj) => Ok((*obj).clone()),
-        None => Err(EvalError::new(format!("identifier not found: {}", id))),
+fn eval_try_expression(
+    body: &BlockStatement,
+    catch: &Option<(Option<String>, BlockStatement)>,
+    finally: &Option<BlockStatement>,
+    env: &Env,
+) -> Result<Object, Unwind> {
+    let result = match (eval_block_statement(body, env), catch) {
+        (Err(Unwind::Error(err)), Some((param, handler))) => {
+            let mut scope = Environment::new_enclosed_environment(env);
+            if let Some(param) = param {
+                scope.set(param.clone(), Rc::new(Object::Error(Box::new(err))));
+            }
+            eval_block_statement(handler, &Rc::new(RefCell::new(scope)))
+        }
+        (result, _) => result,
+    };
+
+    // `finally` also runs when the try or catch block returns, breaks or
+    // fails, and a return, break or error of its own replaces their outcome
+    if let Some(finally) = finally {
+        eval_block_statement(finally, env)?;
     }
+
+    result
 }
 
+fn eval_match_expression(subject: &Expression, arms: &[MatchArm], env: &Env) -> Result<Object, Unwind> {
+    let value = eval_expression(subject, env)?;
 
-fn eval_literal(lit: &Literal) -> Result<Object, EvalError> {
-    match lit {
-        Literal::Integer(i) => Ok(Object::Integer(*i)),
-        Literal::Boolean(b) => Ok(Object::Boolean(*b)),
+    for arm in arms {
+        let mut bindings = Vec::new();
+        match match_pattern(&arm.pattern, &value, env, &mut bindings) {
+            Ok(()) => {}
+            Err(Mismatch::Invalid(err)) => return Err(err.into()),
+            Err(_) => continue,
+        }
+
+        let mut scope = Environment::new_enclosed_environment(env);
+        for (name, value) in bindings {
+            scope.set(name, Rc::new(value));
+        }
+        let scope = Rc::new(RefCell::new(scope));
+
+        let guard_passed = match &arm.guard {
+            Some(guard) => is_truthy(&eval_expression(guard, &scope)?),
+            None => true,
+        };
+        if !guard_passed {
+            continue;
+        }
+
+        return eval_block_statement(&arm.body, &scope);
     }
-}
 
+    Err(EvalError::with_kind(ErrorKind::MatchError, format!("no match for {}", value)).into())
+}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::parser::*;
+/// Why a value didn't fit a pattern. Only `let` turns it into a message,
+/// so a match arm that fails costs no formatting.
+enum Mismatch<'a> {
+    Elements(usize, usize),
+    AtLeastElements(usize, usize),
+    Fields(usize, usize),
+    NotA(&'static str, &'a Object),
+    MissingKey(&'a Literal),
+    Value(&'a Pattern, &'a Object),
+    // The pattern itself is wrong, such as naming an unknown variant. This
+    // is raised rather than falling through to the next arm.
+    Invalid(EvalError),
+}
 
-    fn apply_test(test_case: &[(&str, &str)]) {
-        let env: Env = Rc::new(Default::default());
+impl From<EvalError> for Mismatch<'_> {
+    fn from(err: EvalError) -> Self {
+        Mismatch::Invalid(err)
+    }
+}
 
-        for (input, expected) in test_case {
-            match parse(input) {
-                Ok(node) => match eval(node, &Rc::clone(&env)) {
-                    Ok(eval_result) => assert_eq!(expected, &format!("{}", eval_result)),
-                    Err(err) => assert_eq!(expected, &format!("{}", err)),
-                },
-                Err(e) => panic!("Parsing Error: {:#?}", e),
+impl fmt::Display for Mismatch<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Mismatch::Elements(expected, got) => write!(f, "expected {} elements, got {}", expected, got),
+            Mismatch::AtLeastElements(expected, got) => {
+                write!(f, "expected at least {} elements, got {}", expected, got)
             }
+            Mismatch::Fields(expected, got) => write!(f, "expected {} fields, got {}", expected, got),
+            Mismatch::NotA(kind, value) => write!(f, "expected {}, got {}", kind, value),
+            Mismatch::MissingKey(key) => write!(f, "missing key {}", key),
+            Mismatch::Value(pattern, value) => write!(f, "expected {}, got {}", pattern, value),
+            Mismatch::Invalid(err) => write!(f, "{}", err),
         }
     }
+}
 
-    #[test]
-    fn test_integer_expressions() {
-        let test_case = [
-            ("5", "5"),
+/// Checks `value` against `pattern`, collecting the names it binds. On a
+/// mismatch the error describes the part of the value that didn't fit.
+fn match_pattern<'a>(
+    pattern: &'a Pattern,
+    value: &'a Object,
+    env: &Env,
+    bindings: &mut Vec<(String, Object)>,
+) -> Result<(), Mismatch<'a>> {
+    match (pattern, value) {
+        (Pattern::Wildcard, _) => Ok(()),
+        (Pattern::Binding(name), _) => {
+            bindings.push((name.clone(), value.clone()));
+            Ok(())
+        }
+        (Pattern::Lit(lit), _) => {
+            let expected = eval_literal(lit).expect("literals evaluate without error");
+            // A literal matches whatever `==` considers equal to it, so `1`
+            // matches `1.0`
+            let equal = eval_infix_expression(&Token::Equal, &expected, value);
+            if matches!(equal, Ok(Object::Boolean(true))) {
+                Ok(())
+            } else {
+                Err(Mismatch::Value(pattern, value))
+            }
+        }
+        (Pattern::Array(patterns, rest), Object::Array(elements)) => {
+            match rest {
+                None if patterns.len() != elements.len() => {
+                    return Err(Mismatch::Elements(patterns.len(), elements.len()));
+                }
+                Some(_) if patterns.len() > elements.len() => {
+                    return Err(Mismatch::AtLeastElements(patterns.len(), elements.len()));
+                }
+                _ => {}
+            }
+            for (pattern, element) in patterns.iter().zip(elements) {
+                match_pattern(pattern, element, env, bindings)?;
+            }
+            // The parser only allows a binding or `_` as the rest pattern
+            if let Some(Pattern::Binding(name)) = rest.as_deref() {
+                let rest_elements = Object::Array(elements[patterns.len()..].to_vec());
+                bindings.push((name.clone(), rest_elements));
+            }
+            Ok(())
+        }
+        (Pattern::Array(..), _) => Err(Mismatch::NotA("an array", value)),
+        (Pattern::Hash(pairs), Object::Hash(hash)) => {
+            for (key, pattern) in pairs {
+                let hash_key = eval_literal(key)
+                    .expect("literals evaluate without error")
+                    .hash_key()
+                    .expect("hash pattern keys are hashable");
+                match hash.get(&hash_key) {
+                    Some(value) => match_pattern(pattern, value, env, bindings)?,
+                    None => return Err(Mismatch::MissingKey(key)),
+                }
+            }
+            Ok(())
+        }
+        (Pattern::Hash(_), _) => Err(Mismatch::NotA("a hash", value)),
+        (Pattern::Variant(enum_name, variant, patterns), _) => {
+            let (enum_ty, variant_index) = lookup_variant(enum_name, variant, env)?;
+            let values = match value {
+                Object::Variant(ty, index, values) if Rc::ptr_eq(ty, &enum_ty) && *index == variant_index => {
+                    values
+                }
+                _ => return Err(Mismatch::Value(pattern, value)),
+            };
+            if patterns.len() != values.len() {
+                return Err(Mismatch::Fields(patterns.len(), values.len()));
+            }
+            for (pattern, value) in patterns.iter().zip(values) {
+                match_pattern(pattern, value, env, bindings)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Resolves the `Enum.Variant` named by a pattern to the enum in scope and
+/// the variant's index, so patterns compare by identity like `is` does.
+fn lookup_variant(enum_name: &str, variant: &str, env: &Env) -> Result<(Rc<EnumType>, usize), EvalError> {
+    let ty = match eval_identifier(enum_name, env)? {
+        Object::EnumType(ty) => ty,
+        _ => {
+            return Err(EvalError::with_kind(
+                ErrorKind::TypeError,
+                format!("{} is not an enum", enum_name),
+            ))
+        }
+    };
+    let index = ty.variants.iter().position(|(name, _)| name == variant).ok_or_else(|| {
+        EvalError::with_kind(
+            ErrorKind::NameError,
+            format!("enum {} has no variant named {}", ty.name, variant),
+        )
+    })?;
+    Ok((ty, index))
+}
+
+fn eval_range_expression(op: &Token, left: &Object, right: &Object) -> Result<Object, EvalError> {
+    match (left, right) {
+        (Object::Integer(start), Object::Integer(end)) => Ok(Object::Range {
+            start: *start,
+            end: *end,
+            inclusive: *op == Token::DotDotEq,
+        }),
+        _ => Err(EvalError::with_kind(ErrorKind::TypeError, format!(
+            "range bounds must be integers: {} {} {}",
+            left, op, right
+        ))),
+    }
+}
+
+fn apply_function(function: &Object, args: &[Object]) -> Result<Object, EvalError> {
+    match function {
+        Object::Function(params, body, env) => {
+            let env = Rc::new(RefCell::new(Environment::new_enclosed_environment(env)));
+            let result = bind_arguments(params, args, &env).and_then(|_| eval_block_statement(body, &env));
+
+            finish_unwind(result)
+        }
+        Object::Builtin(builtin) => builtin.call(args),
+        Object::Constructor(ty, index) => {
+            let fields = ty.variants[*index].1.len();
+            if args.len() != fields {
+                return Err(EvalError::with_kind(ErrorKind::ArgumentError, format!(
+                    "invalid number of arguments: expected={}, got={}",
+                    fields,
+                    args.len()
+                )));
+            }
+            Ok(Object::Variant(Rc::clone(ty), *index, args.to_vec()))
+        }
+        f => Err(EvalError::with_kind(ErrorKind::TypeError, format!("not a function: {}", f))),
+    }
+}
+
+/// Checks that `val` can implement the binary operator `symbol`, so a bad
+/// declaration fails where it is written rather than at every use.
+fn check_operator_function(symbol: &str, val: &Object) -> Result<(), EvalError> {
+    let binary = match val {
+        Object::Function(params, _, _) => check_arity(params, 2).is_ok(),
+        Object::Constructor(ty, index) => ty.variants[*index].1.len() == 2,
+        // Builtins check their own arguments when called
+        Object::Builtin(_) => true,
+        val => {
+            return Err(EvalError::with_kind(
+                ErrorKind::TypeError,
+                format!("cannot declare operator {}: {} is not a function", symbol, val),
+            ))
+        }
+    };
+
+    if binary {
+        Ok(())
+    } else {
+        Err(EvalError::with_kind(
+            ErrorKind::ArgumentError,
+            format!("cannot declare operator {}: its function must take two arguments", symbol),
+        ))
+    }
+}
+
+/// Binds call arguments to parameters in the function's environment.
+/// Defaults are evaluated there too, so they can refer to earlier
+/// parameters.
+fn bind_arguments(params: &[Parameter], args: &[Object], env: &Env) -> Result<(), Unwind> {
+    check_arity(params, args.len())?;
+
+    let mut args = args.iter();
+    for param in params {
+        let (name, value) = match param {
+            Parameter::Required(name) => (name, args.next().cloned().expect("arity was checked")),
+            Parameter::Optional(name, default) => match args.next() {
+                Some(arg) => (name, arg.clone()),
+                None => (name, eval_expression(default, env)?),
+            },
+            Parameter::Rest(name) => (name, Object::Array(args.by_ref().cloned().collect())),
+        };
+        env.borrow_mut().set(name.clone(), Rc::new(value));
+    }
+
+    Ok(())
+}
+
+fn check_arity(params: &[Parameter], got: usize) -> Result<(), EvalError> {
+    let min = params
+        .iter()
+        .rposition(|param| matches!(param, Parameter::Required(_)))
+        .map_or(0, |i| i + 1);
+    let has_rest = matches!(params.last(), Some(Parameter::Rest(_)));
+    let max = if has_rest { params.len() - 1 } else { params.len() };
+
+    if got >= min && (has_rest || got <= max) {
+        return Ok(());
+    }
+
+    let expected = if has_rest {
+        format!("at least {}", min)
+    } else if min == max {
+        min.to_string()
+    } else {
+        format!("{} to {}", min, max)
+    };
+    Err(EvalError::with_kind(ErrorKind::ArgumentError, format!(
+        "invalid number of arguments: expected={}, got={}",
+        expected, got
+    )))
+}
+
+fn eval_bang_operator(expr: &Object) -> Result<Object, EvalError> {
+    match *expr {
+        Object::Boolean(b) => Ok(Object::Boolean(!b)),
+        Object::Null => Ok(Object::Boolean(true)),
+        _ => Ok(Object::Boolean(false)),
+    }
+}
+
+fn eval_minus_prefix_operator(expr: &Object) -> Result<Object, EvalError> {
+    match *expr {
+        Object::Integer(b) => match b.checked_neg() {
+            Some(i) => Ok(Object::Integer(i)),
+            None => Ok(Object::from_bigint(-&BigInt::from(b))),
+        },
+        Object::BigInt(ref b) => Ok(Object::from_bigint(-b)),
+        Object::Float(x) => Ok(Object::Float(-x)),
+        _ => Err(EvalError::with_kind(ErrorKind::TypeError, format!(
+            "unknown operator: -{}",
+            expr
+        ))),
+    }
+}
+
+fn eval_bitwise_not_operator(expr: &Object) -> Result<Object, EvalError> {
+    match expr {
+        Object::Integer(i) => Ok(Object::Integer(!i)),
+        Object::BigInt(i) => Ok(Object::from_bigint(!i)),
+        _ => Err(EvalError::with_kind(ErrorKind::TypeError, format!(
+            "unknown operator: ~{}",
+            expr
+        ))),
+    }
+}
+
+fn eval_identifier(id: &str, env: &Env) -> Result<Object, EvalError> {
+    if let Some(obj) = env.borrow().get(id) {
+        return Ok((*obj).clone());
+    }
+
+    match builtins::lookup(id) {
+        Some(builtin) => Ok(builtin),
+        None => Err(EvalError::with_kind(ErrorKind::NameError, format!("identifier not found: {}", id))),
+    }
+}
+
+
+fn eval_literal(lit: &Literal) -> Result<Object, EvalError> {
+    match lit {
+        Literal::Integer(i) => Ok(Object::Integer(*i)),
+        Literal::BigInt(i) => Ok(Object::from_bigint(i.clone())),
+        Literal::Float(x) => Ok(Object::Float(*x)),
+        Literal::Boolean(b) => Ok(Object::Boolean(*b)),
+        Literal::String(s) => Ok(Object::String(s.clone())),
+        Literal::Null => Ok(Object::Null),
+    }
+}
+
+fn eval_template(segments: &[TemplateSegment], env: &Env) -> Result<Object, Unwind> {
+    let mut result = String::new();
+
+    for segment in segments {
+        match segment {
+            TemplateSegment::Text(text) => result.push_str(text),
+            TemplateSegment::Expr(expr) => {
+                let val = eval_expression(expr, env)?;
+                result.push_str(&val.to_string());
+            }
+        }
+    }
+
+    Ok(Object::String(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::*;
+
+    fn apply_test(test_case: &[(&str, &str)]) {
+        let env: Env = Rc::new(Default::default());
+
+        for (input, expected) in test_case {
+            let operators = env.borrow().operators();
+            match parse_with_operators(input, &operators) {
+                Ok(node) => match eval(node, &Rc::clone(&env)) {
+                    Ok(eval_result) => assert_eq!(expected, &format!("{}", eval_result)),
+                    Err(err) => assert_eq!(expected, &format!("{}", err)),
+                },
+                Err(e) => panic!("Parsing Error: {:#?}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_integer_expressions() {
+        let test_case = [
+            ("5", "5"),
             ("10", "10"),
             ("-5", "-5"),
             ("-10", "-10"),
@@ -298,6 +1305,74 @@ mod tests {
         apply_test(&test_case);
     }
 
+    #[test]
+    fn test_checked_integer_arithmetic() {
+        let test_case = [
+            ("5 / 0", "division by zero: 5 / 0"),
+            ("let zero = 1 - 1; 10 / zero", "division by zero: 10 / 0"),
+            ("99999999999999999999 / 0", "division by zero: 99999999999999999999 / 0"),
+            ("3000000000 * 3", "9000000000"),
+            ("9223372036854775807", "9223372036854775807"),
+            ("-7 / 2", "-3"),
+        ];
+
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_big_integer_arithmetic() {
+        let test_case = [
+            ("9223372036854775807 + 1", "9223372036854775808"),
+            ("-9223372036854775807 - 2", "-9223372036854775809"),
+            ("4611686018427387904 * 2", "9223372036854775808"),
+            ("let min = -9223372036854775807 - 1; -min", "9223372036854775808"),
+            ("let min = -9223372036854775807 - 1; min / -1", "9223372036854775808"),
+            ("-9223372036854775808", "-9223372036854775808"),
+            ("9223372036854775808 - 1", "9223372036854775807"),
+            ("123456789012345678901234567890 * 987654321098765432109876543210", "121932631137021795226185032733622923332237463801111263526900"),
+            ("100000000000000000000 / 3", "33333333333333333333"),
+            ("100000000000000000000 / 100000000000000000000", "1"),
+            (
+                "let fact = fn(n) { if (n < 2) { 1 } else { n * fact(n - 1) } }; fact(30)",
+                "265252859812191058636308480000000",
+            ),
+            ("99999999999999999999 > 1", "true"),
+            ("-99999999999999999999 < 1", "true"),
+            ("99999999999999999999 == 99999999999999999999", "true"),
+            ("99999999999999999999 + 1 != 100000000000000000000", "false"),
+            ("99999999999999999999 * 1.0", "1e20"),
+            ("{99999999999999999999: \"big\"}[99999999999999999998 + 1]", "big"),
+            ("[1][99999999999999999999]", "null"),
+        ];
+
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_float_expressions() {
+        let test_case = [
+            ("3.14", "3.14"),
+            ("-2.5", "-2.5"),
+            ("1.5e2", "150.0"),
+            ("0.1 + 0.2", "0.30000000000000004"),
+            ("2.5 * 2.0", "5.0"),
+            ("1 + 0.5", "1.5"),
+            ("0.5 + 1", "1.5"),
+            ("10 / 4.0", "2.5"),
+            ("10 / 4", "2"),
+            ("2 * 1.5 - 1", "2.0"),
+            ("1.0 / 0", "inf"),
+            ("1 < 1.5", "true"),
+            ("2.5 > 3", "false"),
+            ("1 == 1.0", "true"),
+            ("1.5 != 1.5", "false"),
+            ("let price = 19.99; let qty = 3; price * qty", "59.97"),
+            ("1.5 + true", "type mismatch: 1.5 + true"),
+        ];
+
+        apply_test(&test_case);
+    }
+
     #[test]
     fn test_boolean_expressions() {
         let test_case = [
@@ -325,6 +1400,254 @@ mod tests {
         apply_test(&test_case);
     }
 
+    #[test]
+    fn test_string_expressions() {
+        let test_case = [
+            ("\"Hello World!\"", "Hello World!"),
+            ("\"Hello\" + \" \" + \"World!\"", "Hello World!"),
+            ("let name = \"Monkey\"; \"Hi, \" + name", "Hi, Monkey"),
+            ("\"monkey\" == \"monkey\"", "true"),
+            ("\"monkey\" == \"ape\"", "false"),
+            ("\"monkey\" != \"ape\"", "true"),
+            ("\"a\" - \"b\"", "unknown operator: a - b"),
+            ("\"a\" + 1", "type mismatch: a + 1"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        let test_case = [
+            ("let name = \"Monkey\"; \"hello ${name}\"", "hello Monkey"),
+            ("let x = 2; \"${x} * 3 = ${x * 3}\"", "2 * 3 = 6"),
+            ("let f = fn(n) { n + 1 }; \"f(${1}) = ${f(1)}\"", "f(1) = 2"),
+            ("\"tab\\tnew\\nline \\u{1F412}\"", "tab\tnew\nline \u{1F412}"),
+            ("\"\"\"raw ${x} \\n\"\"\"", "raw ${x} \\n"),
+            ("\"${missing}\"", "identifier not found: missing"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_array_literals() {
+        let test_case = [
+            ("[]", "[]"),
+            ("[1, 2 * 2, 3 + 3]", "[1, 4, 6]"),
+            ("[\"a\", true, [1]]", "[a, true, [1]]"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_array_index_expressions() {
+        let test_case = [
+            ("[1, 2, 3][0]", "1"),
+            ("[1, 2, 3][1]", "2"),
+            ("[1, 2, 3][2]", "3"),
+            ("let i = 0; [1][i];", "1"),
+            ("[1, 2, 3][1 + 1];", "3"),
+            ("let myArray = [1, 2, 3]; myArray[2];", "3"),
+            (
+                "let myArray = [1, 2, 3]; myArray[0] + myArray[1] + myArray[2];",
+                "6",
+            ),
+            ("let myArray = [1, 2, 3]; let i = myArray[0]; myArray[i]", "2"),
+            ("[[1, 2], [3]][0][1]", "2"),
+            ("[1, 2, 3][3]", "null"),
+            ("[1, 2, 3][-1]", "null"),
+            ("1[0]", "index operator not supported: 1[0]"),
+            ("[1][true]", "index operator not supported: [1][true]"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_hash_literals() {
+        let test_case = [
+            ("{}", "{}"),
+            (
+                "let two = \"two\"; \
+                 {\"one\": 10 - 9, two: 1 + 1, \"thr\" + \"ee\": 6 / 2, 4: 4, true: 5, false: 6}",
+                "{4: 4, false: 6, true: 5, one: 1, three: 3, two: 2}",
+            ),
+            ("{\"a\": 1, \"a\": 2}", "{a: 2}"),
+            ("{\"nested\": {\"k\": [1, 2]}}", "{nested: {k: [1, 2]}}"),
+            ("{[1]: 2}", "unusable as hash key: [1]"),
+            ("{fn(x) { x }: 2}", "unusable as hash key: fn(x) {...}"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_hash_index_expressions() {
+        let test_case = [
+            ("{\"foo\": 5}[\"foo\"]", "5"),
+            ("{\"foo\": 5}[\"bar\"]", "null"),
+            ("let key = \"foo\"; {\"foo\": 5}[key]", "5"),
+            ("{}[\"foo\"]", "null"),
+            ("{5: 5}[5]", "5"),
+            ("{true: 5}[true]", "5"),
+            ("{false: 5}[false]", "5"),
+            ("let p = {\"name\": \"x\", 1: true}; p[\"name\"]", "x"),
+            ("{\"name\": \"Monkey\"}[fn(x) { x }];", "unusable as hash key: fn(x) {...}"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_builtin_functions() {
+        let test_case = [
+            ("len(\"\")", "0"),
+            ("len(\"four\")", "4"),
+            ("len(\"hello world\")", "11"),
+            ("len([1, 2, 3])", "3"),
+            ("len({\"a\": 1})", "1"),
+            ("len(1)", "argument to `len` not supported, got 1"),
+            ("len(\"one\", \"two\")", "wrong number of arguments to `len`: got=2, want=1"),
+            ("first([1, 2, 3])", "1"),
+            ("first([])", "null"),
+            ("first(1)", "argument to `first` not supported, got 1"),
+            ("last([1, 2, 3])", "3"),
+            ("last([])", "null"),
+            ("rest([1, 2, 3])", "[2, 3]"),
+            ("rest([])", "null"),
+            ("push([], 1)", "[1]"),
+            ("let a = [1]; push(a, 2); a", "[1]"),
+            ("push(1, 1)", "argument to `push` not supported, got 1"),
+            ("puts(\"hello\")", "null"),
+            ("len", "builtin function len"),
+            ("let len = fn(x) { 42 }; len([1])", "42"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_register_builtin() {
+        builtins::register("double", |args| match args {
+            [Object::Integer(i)] => Ok(Object::Integer(i * 2)),
+            _ => Err(EvalError::new("double expects an integer".to_string())),
+        });
+
+        let test_case = [
+            ("double(21)", "42"),
+            ("double(true)", "double expects an integer"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_comparison_and_modulo_operators() {
+        let test_case = [
+            ("1 <= 2", "true"),
+            ("2 <= 2", "true"),
+            ("3 <= 2", "false"),
+            ("1 >= 2", "false"),
+            ("2 >= 2", "true"),
+            ("1.5 >= 1", "true"),
+            ("99999999999999999999 >= 99999999999999999999", "true"),
+            ("7 % 3", "1"),
+            ("-7 % 3", "-1"),
+            ("7 % -3", "1"),
+            ("7.5 % 2", "1.5"),
+            ("100000000000000000001 % 10", "1"),
+            ("let min = -9223372036854775807 - 1; min % -1", "0"),
+            ("5 % 0", "division by zero: 5 % 0"),
+            ("true <= false", "unknown operator: true <= false"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        let test_case = [
+            ("0xFF & 0x0F", "15"),
+            ("0b1010 | 0b0101", "15"),
+            ("0xFF ^ 0x0F", "240"),
+            ("~0", "-1"),
+            ("~0b1010", "-11"),
+            ("1 << 4", "16"),
+            ("256 >> 4", "16"),
+            ("-16 >> 2", "-4"),
+            ("-1 >> 100", "-1"),
+            ("1 >> 64", "0"),
+            ("1 << 63", "9223372036854775808"),
+            ("1 << 100", "1267650600228229401496703205376"),
+            ("-1 << 64", "-18446744073709551616"),
+            ("(1 << 100) >> 99", "2"),
+            ("(1 << 64) | 1", "18446744073709551617"),
+            ("~(1 << 64)", "-18446744073709551617"),
+            ("0 << 1000", "0"),
+            ("1 << -1", "negative shift amount: -1"),
+            ("1 << (1 << 40)", "shift amount too large: 1099511627776"),
+            ("let flags = 0b0101; let mask = 1 << 2; flags & mask != 0", "true"),
+            ("1_000_000 + 0o755", "1000493"),
+            ("1.5 & 1", "unknown operator: 1.5 & 1.0"),
+            ("~true", "unknown operator: ~true"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_null_expressions() {
+        let test_case = [
+            ("null", "null"),
+            ("let x = null; x", "null"),
+            ("!null", "true"),
+            ("null ?? 5", "5"),
+            ("0 ?? 5", "0"),
+            ("false ?? 5", "false"),
+            ("null ?? null ?? \"default\"", "default"),
+            ("let config = {\"port\": 80}; config[\"host\"] ?? \"localhost\"", "localhost"),
+            ("let config = {\"port\": 80}; config[\"port\"] ?? 8080", "80"),
+            ("1 ?? undefined_fn()", "1"),
+            ("null ?? undefined_fn()", "identifier not found: undefined_fn"),
+            ("let f = fn(x) { x * 2 }; f?.(21)", "42"),
+            ("let f = null; f?.(21)", "null"),
+            ("let f = null; f?.(undefined_fn())", "null"),
+            ("let hooks = {}; hooks[\"onLoad\"]?.(1) ?? \"no hook\"", "no hook"),
+            ("len?.([1, 2])", "2"),
+            ("1?.(2)", "not a function: 1"),
+            ("let f = null; f?.()(1)", "null"),
+            ("let f = null; f?.(1)[0](undefined_fn())", "null"),
+            ("let f = null; [f?.().x, f?.()[0] ?? \"none\"]", "[null, none]"),
+            ("let g = fn() { fn(x) { x + 1 } }; g?.()(1)", "2"),
+            ("let f = null; f(1)?.(2)", "not a function: null"),
+            ("(null?.(1))(2)", "not a function: null"),
+            ("let f = null; [(f?.())?.(1), (f?.()) ?? 0]", "[null, 0]"),
+            ("let f = null; (f?.()).x", "cannot access field x on null"),
+            ("let f = null; (f?.().x)[0]", "index operator not supported: null[0]"),
+            ("[null == null, null != null, 1 != null, null == 0]", "[true, false, true, false]"),
+            ("[\"\" == null, null != \"\"]", "[false, true]"),
+            ("let x = null; if (x == null) { \"missing\" } else { x }", "missing"),
+            ("[null == false, [1] != null, fn() {} == null]", "[false, true, false]"),
+            ("null < 1", "type mismatch: null < 1"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_logical_operators() {
+        let test_case = [
+            ("true && true", "true"),
+            ("true && false", "false"),
+            ("false || true", "true"),
+            ("false || false", "false"),
+            ("1 < 2 && 2 < 3", "true"),
+            ("1 > 2 || 2 > 3", "false"),
+            ("1 && \"yes\"", "true"),
+            ("null_value || 1", "identifier not found: null_value"),
+            ("false && undefined_fn()", "false"),
+            ("true || undefined_fn()", "true"),
+            ("true && undefined_fn()", "identifier not found: undefined_fn"),
+            (
+                "let positiveAt = fn(a, i) { i < len(a) && a[i] > 0 }; \
+                 [positiveAt([1, -1], 0), positiveAt([1, -1], 1), positiveAt([1, -1], 5)]",
+                "[true, false, false]",
+            ),
+        ];
+        apply_test(&test_case);
+    }
+
     #[test]
     fn test_bang_operator() {
         let test_case = [
@@ -385,13 +1708,30 @@ mod tests {
             ),
             ("5; true + false; 5", "unknown operator: true + false"),
             (
-                "if (10 > 1) { true + false; )",
+                "if (10 > 1) { true + false; }",
                 "unknown operator: true + false",
             ),
         ];
         apply_test(&test_case);
     }
 
+    #[test]
+    fn test_comments() {
+        let test_case = [
+            (
+                "// Closures
+                 let newAdder = fn(x) {
+                   fn(y) { x + y }; /* returns a closure */
+                 };
+                 let addTwo = newAdder(2);
+                 addTwo(3); // returns 5",
+                "5",
+            ),
+            ("10 /* / 0 */ / 2", "5"),
+        ];
+        apply_test(&test_case);
+    }
+
     #[test]
     fn test_let_statements() {
         let test_case = [
@@ -399,6 +1739,532 @@ mod tests {
             ("let a = 5 * 5; a;", "25"),
             ("let a = 5; let b = a; b;", "5"),
             ("let a = 5; let b = a; let c = a + b + 5; c;", "15"),
+            ("let x1 = 1; let x2 = 2; x1 + x2", "3"),
+            ("let größe = 5; let 名前 = \"猿\"; \"${名前}: ${größe}\"", "猿: 5"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_assignment() {
+        let test_case = [
+            ("let x = 1; x = 2; x", "2"),
+            ("let x = 1; x = x + 1", "2"),
+            ("let a = 0; let b = 0; a = b = 5; [a, b]", "[5, 5]"),
+            ("let x = 10; x += 5; x", "15"),
+            ("let x = 10; x -= 5; x", "5"),
+            ("let x = 10; x *= 5; x", "50"),
+            ("let x = 10; x /= 5; x", "2"),
+            ("let s = \"a\"; s += \"b\"; s", "ab"),
+            ("let x = 1; x /= 0", "division by zero: 1 / 0"),
+            ("y = 1", "cannot assign to unbound identifier: y"),
+            ("y += 1", "identifier not found: y"),
+            ("len = 1", "cannot assign to unbound identifier: len"),
+            (
+                "let newCounter = fn() { \
+                   let count = 0; \
+                   fn() { count += 1; count } \
+                 }; \
+                 let counter = newCounter(); \
+                 counter(); counter(); counter()",
+                "3",
+            ),
+            (
+                "let total = 0; \
+                 let add = fn(n) { total = total + n; }; \
+                 add(5); add(10); total",
+                "15",
+            ),
+            (
+                "let x = 1; \
+                 let shadow = fn() { let x = 2; x = 3; x }; \
+                 [shadow(), x]",
+                "[3, 1]",
+            ),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_destructuring_let() {
+        let test_case = [
+            ("let [a, b] = [1, 2]; a + b", "3"),
+            ("let [head, ...tail] = [1, 2, 3]; [head, tail]", "[1, [2, 3]]"),
+            ("let [only, ...none] = [1]; none", "[]"),
+            ("let [_, [x, y], ..._] = [0, [1, 2], 3, 4]; x * 10 + y", "12"),
+            (
+                "let person = {\"name\": \"Ann\", \"age\": 30, \"city\": \"Oslo\"}; \
+                 let {name, age} = person; \"${name} ${age}\"",
+                "Ann 30",
+            ),
+            ("let {\"pos\": [px, py], 1: one} = {\"pos\": [3, 4], 1: \"x\"}; [px, py, one]", "[3, 4, x]"),
+            ("let divmod = fn(a, b) { [a / b, a % b] }; let [q, r] = divmod(17, 5); [q, r]", "[3, 2]"),
+            ("let [c, d] = [1];", "cannot destructure [1]: expected 2 elements, got 1"),
+            ("let [c, d, ...e] = [1];", "cannot destructure [1]: expected at least 2 elements, got 1"),
+            ("let [c] = 5;", "cannot destructure 5: expected an array, got 5"),
+            ("let {city} = {\"name\": \"Ann\"};", "cannot destructure {name: Ann}: missing key \"city\""),
+            ("let {city} = [1];", "cannot destructure [1]: expected a hash, got [1]"),
+            ("let [c, [0, d]] = [1, [2, 3]];", "cannot destructure [1, [2, 3]]: expected 0, got 2"),
+            ("let [k, ...ks] = [1, 2]; match (ks) { [] => \"none\", [x, ...more] => x }", "2"),
+            ("let {_, n} = {\"_\": 1, \"n\": 2}; n", "2"),
+            ("_", "identifier not found: _"),
+            ("match ({\"a\": 1}) { {_} => 1, {a} => a + 1 }", "2"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_else_if_expression() {
+        let test_case = [
+            ("let sign = fn(x) { if (x < 0) { -1 } else if (x > 0) { 1 } else { 0 } }; sign(-5)", "-1"),
+            ("sign(5)", "1"),
+            ("sign(0)", "0"),
+            ("if (false) { 1 } else if (false) { 2 }", "null"),
+            ("if (false) { 1 } else if (true) { return 2; 3 } else { 4 }", "2"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_match_expression() {
+        let test_case = [
+            (
+                "let describe = fn(x) { \
+                   match (x) { \
+                     0 => \"zero\", \
+                     -1 => \"minus one\", \
+                     \"hi\" => \"greeting\", \
+                     true => \"yes\", \
+                     null => \"nothing\", \
+                     [] => \"empty\", \
+                     [a] => \"one: ${a}\", \
+                     [a, [b, _]] => \"nested: ${a + b}\", \
+                     {\"name\": n, \"age\": 1} => \"baby ${n}\", \
+                     {\"name\": n} => \"named ${n}\", \
+                     n if n > 100 => { let d = n / 100; \"big ${d}\" } \
+                     _ => \"other\" \
+                   } \
+                 }; describe(0)",
+                "zero",
+            ),
+            ("describe(-1)", "minus one"),
+            ("describe(\"hi\")", "greeting"),
+            ("describe(true)", "yes"),
+            ("describe(null)", "nothing"),
+            ("describe([])", "empty"),
+            ("describe([7])", "one: 7"),
+            ("describe([1, [2, 3]])", "nested: 3"),
+            ("describe([1, 2, 3])", "type mismatch: [1, 2, 3] > 100"),
+            ("describe({\"name\": \"Ann\", \"age\": 1})", "baby Ann"),
+            ("describe({\"name\": \"Bob\", \"age\": 40})", "named Bob"),
+            ("describe(250)", "big 2"),
+            ("describe(50)", "other"),
+            ("describe(1.0)", "other"),
+            ("match (2.5) { 2.5 => \"float\" }", "float"),
+            ("describe(0.0)", "zero"),
+            ("match (1) { 1.0 => \"one\" }", "one"),
+            ("match (9223372036854775808) { 9223372036854775808.0 => \"big\" }", "big"),
+            ("match (true) { 1 => \"int\", _ => \"other\" }", "other"),
+            ("match (\"1\") { 1 => \"int\", _ => \"other\" }", "other"),
+            ("let x = 1; match (5) { x => x }; x", "1"),
+            ("match (3) { 1 => 1, 2 => 2 }", "no match for 3"),
+            ("match ([1, 2]) { [a, b] if a > b => 1 }", "no match for [1, 2]"),
+            ("match (1) { _ => y }", "identifier not found: y"),
+            ("let total = 0; for (i in 0..5) { match (i) { 3 => { break; }, _ => { total += i; } } }; total", "3"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_structs() {
+        let test_case = [
+            (
+                "struct Point { x, y, fn norm() { self.x + self.y }, fn add(other) { Point { x: self.x + other.x, y: self.y + other.y } } }",
+                "struct Point",
+            ),
+            ("let p = Point { x: 1, y: 2 }; p", "Point { x: 1, y: 2 }"),
+            ("Point { y: 4, x: 3 }", "Point { x: 3, y: 4 }"),
+            ("let x = 5; let y = 6; Point { x, y }.y", "6"),
+            ("[p.x, p.y]", "[1, 2]"),
+            ("p.norm()", "3"),
+            ("p.add(Point { x: 10, y: 20 })", "Point { x: 11, y: 22 }"),
+            ("p.add(p).add(p).norm()", "9"),
+            ("let norm = p.norm; norm()", "3"),
+            ("let ps = [p, Point { x: 5, y: 5 }]; ps[1].norm() + ps[0].x", "11"),
+            ("p.z", "Point has no field or method named z"),
+            ("Point { x: 1, z: 2 }", "Point has no field or method named z"),
+            ("Point { x: 1 }", "missing field y for Point"),
+            ("Point { x: 1, x: 2, y: 3 }", "duplicate field x for Point"),
+            ("Nope { x: 1 }", "identifier not found: Nope"),
+            ("let q = 1; q { x: 1 }", "q is not a struct"),
+            ("try { p.z } catch (e) { e[\"kind\"] }", "NameError"),
+            ("match (1) { n if n == (Point { x: n, y: 2 }).x => \"x\", _ => \"no\" }", "x"),
+            ("match (2) { n if n == Point { x: 1, y: n }.y => \"y\", _ => \"no\" }", "y"),
+            ("[Point { x: 1, y: 2 }.x, -Point { x: 1, y: 2 }.y]", "[1, -2]"),
+            ("struct Counter { n, fn next() { Counter { n: self.n + 1 } } }; Counter { n: 0 }.next().next().n", "2"),
+            ("let scale = 3; struct Scaled { v, fn get() { self.v * scale } }; Scaled { v: 2 }.get()", "6"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_enums() {
+        let test_case = [
+            ("enum Shape { Circle(r), Rect(w, h), Empty }", "enum Shape"),
+            ("Shape.Circle(3)", "Circle(3)"),
+            ("Shape.Rect(2, \"x\")", "Rect(2, x)"),
+            ("Shape.Empty", "Empty"),
+            ("Shape.Circle", "constructor Shape.Circle"),
+            (
+                "let c = Shape.Circle(3); [c is Shape.Circle, c is Shape.Rect, c is Shape.Empty, c is Shape]",
+                "[true, false, false, true]",
+            ),
+            ("[Shape.Empty is Shape.Empty, 5 is Shape.Circle, 5 is Shape]", "[true, false, false]"),
+            ("c.r", "3"),
+            ("let Shape.Circle(r) = c; r", "3"),
+            (
+                "let Shape.Rect(w, h) = c;",
+                "cannot destructure Circle(3): expected Shape.Rect(w, h), got Circle(3)",
+            ),
+            ("let Shape.Circle(a, b) = c;", "cannot destructure Circle(3): expected 2 fields, got 1"),
+            (
+                "let area = fn(s) { match (s) { Shape.Circle(r) => 3 * r * r, Shape.Rect(w, h) => w * h, Shape.Empty => 0 } }; \
+                 [area(c), area(Shape.Rect(2, 5)), area(Shape.Empty)]",
+                "[27, 10, 0]",
+            ),
+            ("Shape.Triangle", "enum Shape has no variant named Triangle"),
+            ("c.w", "Shape.Circle has no field named w"),
+            ("Shape.Rect(1)", "invalid number of arguments: expected=2, got=1"),
+            ("c is 5", "expected a type or variant after is, got 5"),
+            (
+                "enum Option { Some(value), None }; \
+                 let find = fn(xs, x) { for (i, v in xs) { if (v == x) { return Option.Some(i); } }; Option.None }; \
+                 [find([4, 5, 6], 6), find([4, 5], 9)]",
+                "[Some(2), None]",
+            ),
+            ("6 |> Option.Some", "Some(6)"),
+            ("struct P { x }; [P { x: 1 } is P, c is P]", "[true, false]"),
+            ("match (c) { Shape.Typo => 1, _ => 2 }", "enum Shape has no variant named Typo"),
+            ("let Shape.Typo(x) = c;", "enum Shape has no variant named Typo"),
+            ("match (c) { Nope.Circle(r) => r, _ => 0 }", "identifier not found: Nope"),
+            ("match (c) { c.Circle(r) => r, _ => 0 }", "c is not an enum"),
+            ("match (5) { Shape.Empty => 1, _ => 2 }", "2"),
+            // A redeclared enum is a new type, which patterns tell apart like `is`
+            (
+                "let s = c; enum Shape { Circle(r) }; \
+                 [s is Shape.Circle, match (s) { Shape.Circle(r) => 1, _ => 2 }]",
+                "[false, 2]",
+            ),
+            (
+                "[Option.None == Option.None, Option.Some(1) == Option.Some(1.0), \
+                 Option.Some(1) != Option.Some(2)]",
+                "[true, true, true]",
+            ),
+            ("[Option.Some(null) == Option.Some(null), Option.Some(1) == Option.None]", "[true, false]"),
+            (
+                "[P { x: 1 } == P { x: 1 }, P { x: 1 } != P { x: 2 }, P { x: 1 } == Option.None]",
+                "[true, true, false]",
+            ),
+            ("struct Q { x }; P { x: 1 } == Q { x: 1 }", "false"),
+            (
+                "[Option.Some([1]) == Option.Some([1]), Option.Some({}) != Option.Some({})]",
+                "[false, true]",
+            ),
+            (
+                "struct R { f }; let f = fn() {}; [R { f: f } == R { f: f }, P { x: [1] } == P { x: [1] }]",
+                "[false, false]",
+            ),
+            ("Option.None == 1", "type mismatch: None == 1"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_infix_operators() {
+        let test_case = [
+            ("infix 6 <+> = fn(a, b) { [a, b] }; 1 <+> 2", "[1, 2]"),
+            ("1 <+> 2 <+> 3", "[[1, 2], 3]"),
+            ("1 <+> 2 * 3", "[1, 6]"),
+            ("infix 8 ** = fn(a, b) { let r = 1; for (_ in 0..b) { r *= a; }; r }; 2 ** 10 - 1", "1023"),
+            (
+                "infix 4 <=> = |a, b| if (a < b) { -1 } else if (a > b) { 1 } else { 0 }; [1 <=> 2, 2 <=> 2]",
+                "[-1, 0]",
+            ),
+            ("3 <=> 1 == 1", "true"),
+            (
+                "struct V { x, y }; infix 6 |+| = |a, b| V { x: a.x + b.x, y: a.y + b.y }; \
+                 V { x: 1, y: 2 } |+| V { x: 10, y: 20 }",
+                "V { x: 11, y: 22 }",
+            ),
+            ("let f = fn() { 1 <+> 2 }; f()", "[1, 2]"),
+            ("infix 6 <*> = 5;", "cannot declare operator <*>: 5 is not a function"),
+            (
+                "infix 6 <-> = fn(a) { a };",
+                "cannot declare operator <->: its function must take two arguments",
+            ),
+            (
+                "infix 6 <~> = fn(a, b, c) { a };",
+                "cannot declare operator <~>: its function must take two arguments",
+            ),
+            ("infix 6 <~> = fn(a, b = 1, c = 2) { a + b + c }; 1 <~> 2", "5"),
+            ("infix 6 <@> = fn(...xs) { xs }; 1 <@> 2", "[1, 2]"),
+            ("enum L { Cons(h, t), Nil }; infix 5 <#> = L.Cons; 1 <#> L.Nil", "Cons(1, Nil)"),
+            ("infix 6 <|> = push; [1] <|> 2", "[1, 2]"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_operators_declared_by_evaluation() {
+        let env: Env = Rc::new(Default::default());
+        let run = |input: &str| {
+            let operators = env.borrow().operators();
+            match parse_with_operators(input, &operators) {
+                Ok(node) => match eval(node, &env) {
+                    Ok(evaluated) => evaluated.to_string(),
+                    Err(err) => err.to_string(),
+                },
+                Err(errors) => errors[0].to_string(),
+            }
+        };
+
+        // Parsing alone declares nothing, and neither does a declaration
+        // the program never reaches
+        let _ = parse("infix 6 <%> = fn(a, b) { a % b };");
+        assert_eq!("No prefix parse function for % is found", run("7 <%> 4"));
+        assert_eq!("identifier not found: nope", run("nope; infix 6 <%> = fn(a, b) { a % b };"));
+        assert_eq!("No prefix parse function for % is found", run("7 <%> 4"));
+
+        assert_eq!("3", run("infix 6 <%> = fn(a, b) { a % b }; 7 <%> 4"));
+        assert_eq!("2", run("7 <%> 5"));
+
+        // Symbols that built-in operators already spell can't be declared,
+        // so code using them keeps its meaning
+        assert_eq!("cannot declare operator <-: it already reads as < -", run("infix 6 <- = f;"));
+        assert_eq!("cannot declare operator !!: it already reads as ! !", run("infix 6 !! = f;"));
+        assert_eq!("[false, true]", run("let x = 1; [x<-1, !!true]"));
+
+        // Another interpreter has its own operators
+        let other: Env = Rc::new(Default::default());
+        let operators = other.borrow().operators();
+        assert!(parse_with_operators("7 <%> 4", &operators).is_err());
+    }
+
+    #[test]
+    fn test_try_catch() {
+        let test_case = [
+            ("try { 1 + true } catch (e) { e }", "TypeError: type mismatch: 1 + true"),
+            ("try { missing } catch (e) { e[\"kind\"] }", "NameError"),
+            (
+                "try { missing } catch (e) { [e.kind, e.message, e.data] }",
+                "[NameError, identifier not found: missing, null]",
+            ),
+            ("try { throw 42 } catch (e) { e.data + 1 }", "43"),
+            ("try { missing } catch (e) { e.nope }", "error has no field named nope"),
+            ("try { fn(a) { a }() } catch (e) { e[\"message\"] }", "invalid number of arguments: expected=1, got=0"),
+            ("try { len(1, 2) } catch (e) { e[\"kind\"] }", "ArgumentError"),
+            ("try { 1 / 0 } catch (e) { e[\"kind\"] }", "ArithmeticError"),
+            ("try { match (1) { 2 => 2 } } catch (e) { e[\"kind\"] }", "MatchError"),
+            ("try { 5 } catch (e) { 0 }", "5"),
+            ("try { throw \"oops\"; } catch (e) { [e[\"kind\"], e[\"message\"], e[\"data\"]] }", "[Error, oops, oops]"),
+            ("try { throw {\"code\": 42}; } catch (e) { e[\"data\"][\"code\"] }", "42"),
+            (
+                "try { throw error(\"bad input\", \"ValueError\", [1, 2]); } catch (e) { [e, e[\"data\"]] }",
+                "[ValueError: bad input, [1, 2]]",
+            ),
+            ("try { throw error(\"plain\"); } catch (e) { [e[\"kind\"], e[\"data\"]] }", "[Error, null]"),
+            ("try { throw 1; } catch { \"handled\" }", "handled"),
+            ("let log = []; try { log = push(log, 1); } finally { log = push(log, 2); }; log", "[1, 2]"),
+            ("let log = []; try { throw \"x\"; } catch (e) { log = push(log, e); } finally { log = push(log, \"done\"); }; log", "[Error: x, done]"),
+            ("let log = []; try { try { throw \"inner\"; } finally { log = push(log, 1); } } catch (e) { log = push(log, e[\"message\"]); }; log", "[1, inner]"),
+            ("try { throw \"x\"; } finally { 1 }", "x"),
+            ("try { throw \"x\"; } catch (e) { throw error(\"wrapped\", \"WrapError\", e); }", "wrapped"),
+            (
+                "let log = []; let f = fn() { try { return 1; } finally { log = push(log, \"cleanup\"); } }; [f(), log]",
+                "[1, [cleanup]]",
+            ),
+            ("let g = fn() { try { return 1; } finally { return 2; } }; g()", "2"),
+            (
+                "let n = 0; for (i in 0..5) { try { if (i == 2) { break; } } finally { n += 1; } }; n",
+                "3",
+            ),
+            ("let h = fn() { try { throw \"x\"; } catch (e) { return e[\"message\"]; } }; h()", "x"),
+            ("try { 1 } catch (e) { 2 }; e", "identifier not found: e"),
+            ("error(1)", "argument to `error` not supported, got 1"),
+            ("error()", "wrong number of arguments to `error`: got=0, want=1 to 3"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_while_expression() {
+        let test_case = [
+            ("let i = 0; while (i < 5) { i += 1; }; i", "5"),
+            ("let i = 0; while (i < 5) { i += 1; }", "null"),
+            ("while (false) { 1 }", "null"),
+            ("let i = 0; while (true) { i += 1; if (i == 3) { break; } }; i", "3"),
+            (
+                "let i = 0; let sum = 0; \
+                 while (i < 10) { i += 1; if (i % 2 == 0) { continue; } sum += i; }; sum",
+                "25",
+            ),
+            (
+                "let i = 0; let n = 0; \
+                 while (i < 3) { i += 1; let j = 0; while (true) { j += 1; n += 1; if (j == 2) { break; } } }; n",
+                "6",
+            ),
+            (
+                "let find = fn(limit) { let i = 0; while (true) { if (i * i > limit) { return i; } i += 1; } }; \
+                 find(50)",
+                "8",
+            ),
+            ("while (x) { 1 }", "identifier not found: x"),
+            // Signals unwind to the loop or function instead of becoming values
+            ("let i = 0; while (true) { i += 1; let x = if (i == 3) { break; }; }; i", "3"),
+            (
+                "let i = 0; let seen = []; \
+                 while (i < 4) { i += 1; let x = if (i % 2 == 0) { continue; } else { i }; seen = push(seen, x); }; seen",
+                "[1, 3]",
+            ),
+            ("let i = 0; while (i < 5) { i += 1; [1, if (i < 9) { break; }]; }; i", "1"),
+            ("let f = fn() { let x = if (true) { return 1; }; 2 }; f()", "1"),
+            ("let f = fn() { 1 + if (true) { return 5; } }; f()", "5"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_loop_control_outside_loop() {
+        // The parser never produces these, so build the nodes by hand
+        let env: Env = Rc::new(Default::default());
+        let stray_break = eval(Node::Stmt(Statement::Break), &env);
+        assert_eq!("break outside of a loop", stray_break.unwrap_err().to_string());
+
+        let function = Object::Function(vec![], vec![Statement::Continue], Rc::clone(&env));
+        let stray_continue = apply_function(&function, &[]);
+        assert_eq!("continue outside of a loop", stray_continue.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_for_expression() {
+        let test_case = [
+            ("let sum = 0; for (x in [1, 2, 3]) { sum += x; }; sum", "6"),
+            ("let sum = 0; for (i in 1..=100) { sum += i; }; sum", "5050"),
+            ("let n = 0; for (i in 5..0) { n += 1; }; n", "0"),
+            ("for (x in [1]) { x }", "null"),
+            ("let s = \"\"; for (c in \"abc\") { s = c + s; }; s", "cba"),
+            ("let ks = []; for (k in {\"b\": 2, \"a\": 1}) { ks = push(ks, k); }; ks", "[a, b]"),
+            (
+                "let out = []; for (k, v in {\"a\": 1, \"b\": 2}) { out = push(out, k + \"=\" + \"${v}\"); }; out",
+                "[a=1, b=2]",
+            ),
+            ("let out = []; for (i, x in [\"a\", \"b\"]) { out = push(out, i); }; out", "[0, 1]"),
+            (
+                "let out = []; for (i in 0..10) { if (i % 3 != 0) { continue; } if (i > 6) { break; } out = push(out, i); }; out",
+                "[0, 3, 6]",
+            ),
+            (
+                "let firstOver = fn(xs, n) { for (x in xs) { if (x > n) { return x; } } null }; firstOver(0..1000000000000, 41)",
+                "42",
+            ),
+            (
+                "let fs = []; for (i in 0..3) { fs = push(fs, fn() { i }); }; [fs[0](), fs[2]()]",
+                "[0, 2]",
+            ),
+            ("for (x in 5) { x }", "not iterable: 5"),
+            ("for (x in 0..10) { y }", "identifier not found: y"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_ranges() {
+        let test_case = [
+            ("0..10", "0..10"),
+            ("let n = 3; 1..=n * 2", "1..=6"),
+            ("collect(0..5)", "[0, 1, 2, 3, 4]"),
+            ("collect(3..=1)", "[]"),
+            ("collect(\"héé\")", "[h, é, é]"),
+            ("collect({1: true, 2: false})", "[1, 2]"),
+            ("len(0..10)", "10"),
+            ("len(0..=10)", "11"),
+            ("len(10..0)", "0"),
+            ("len(-9223372036854775807 - 1..=9223372036854775807)", "18446744073709551616"),
+            ("collect(1)", "argument to `collect` not supported, got 1"),
+            (
+                "collect(0..9223372036854775807)",
+                "range 0..9223372036854775807 is too large to collect: 9223372036854775807 elements, limit 1048576",
+            ),
+            ("len(collect(0..1048576))", "1048576"),
+            (
+                "[...-5..=1048575]",
+                "range -5..=1048575 is too large to collect: 1048581 elements, limit 1048576",
+            ),
+            ("let n = 0; for (i in 0..9223372036854775807) { n += 1; if (n == 3) { break; } }; n", "3"),
+            ("0..1.5", "range bounds must be integers: 0 .. 1.5"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_function_parameters() {
+        let test_case = [
+            ("let add = fn(a, b) { a + b }; add(1)", "invalid number of arguments: expected=2, got=1"),
+            ("add(1, 2, 3)", "invalid number of arguments: expected=2, got=3"),
+            ("let greet = fn(name, greeting = \"Hello\") { \"${greeting}, ${name}\" }; greet(\"Ann\")", "Hello, Ann"),
+            ("greet(\"Ann\", \"Hi\")", "Hi, Ann"),
+            ("greet()", "invalid number of arguments: expected=1 to 2, got=0"),
+            ("let area = fn(w, h = w) { w * h }; area(3)", "9"),
+            ("let scale = 2; let scaled = fn(x, by = scale) { x * by }; scale = 5; scaled(3)", "15"),
+            ("let count = 0; let tick = fn(n = count += 1) { n }; tick(); tick(); tick(10); count", "2"),
+            ("let sum = fn(first, ...rest) { let total = first; for (x in rest) { total += x; }; total }; sum(1)", "1"),
+            ("sum(1, 2, 3, 4)", "10"),
+            ("sum()", "invalid number of arguments: expected=at least 1, got=0"),
+            ("let all = fn(...xs) { xs }; all()", "[]"),
+            ("let opt = fn(a, b = 2, ...more) { [a, b, more] }; opt(1)", "[1, 2, []]"),
+            ("opt(1, 3, 5, 7)", "[1, 3, [5, 7]]"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_spread_arguments() {
+        let test_case = [
+            ("let add3 = fn(a, b, c) { a + b + c }; let xs = [1, 2, 3]; add3(...xs)", "6"),
+            ("add3(10, ...[20, 30])", "60"),
+            ("add3(...0..3)", "3"),
+            ("add3(...[1], ...[2, 3])", "6"),
+            ("add3(...[1, 2])", "invalid number of arguments: expected=3, got=2"),
+            ("len(...[\"abc\"])", "3"),
+            ("[0, ...xs, ...\"ab\"]", "[0, 1, 2, 3, a, b]"),
+            ("add3(...5)", "cannot spread 5"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_lambdas_and_pipelines() {
+        let test_case = [
+            (
+                "let map = fn(xs, f) { let out = []; for (x in xs) { out = push(out, f(x)); }; out }; \
+                 let filter = fn(xs, keep) { let out = []; for (x in xs) { if (keep(x)) { out = push(out, x); } }; out }; \
+                 map([1, 2, 3], |x| x * 2)",
+                "[2, 4, 6]",
+            ),
+            ("map([1, 2, 3], x => x * x)", "[1, 4, 9]"),
+            ("let isOdd = |n| n % 2 == 1; let double = n => n * 2; 1..=5 |> filter(isOdd) |> map(double)", "[2, 6, 10]"),
+            ("[1, 2, 3] |> len", "3"),
+            ("let answer = || 42; answer()", "42"),
+            ("let add = |a, b = 10| a + b; [add(1), add(1, 2)]", "[11, 3]"),
+            ("let sum = |...xs| { let t = 0; for (x in xs) { t += x; }; t }; 3 |> sum(...[4, 5])", "12"),
+            ("let adder = |x, n| || x + n; 5 |> adder(10)()", "15"),
+            ("let k = 3; let addK = x => x + k; 4 |> addK", "7"),
+            ("match (5) { n if n > 3 => \"big\", _ => \"small\" }", "big"),
+            ("match ([1, 3]) { xs if len(filter(xs, x => x > 2)) > 0 => \"some\", _ => \"none\" }", "some"),
+            ("match (4) { n if (x => x == n)(4) => \"same\", _ => \"other\" }", "same"),
+            ("match (2) { n if [x => x * n][0](3) == 6 => \"six\", _ => \"other\" }", "six"),
+            ("let maybe = null; 1 |> maybe?.()", "null"),
+            ("2 |> 3", "not a function: 3"),
         ];
         apply_test(&test_case);
     }
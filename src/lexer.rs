@@ -1,29 +1,66 @@
-use crate::token::Token;
-use anyhow::Result;
+use crate::bigint::BigInt;
+use crate::token::{TemplatePart, Token};
+use unicode_ident::{is_xid_continue, is_xid_start};
+
+/// Returned by `read_char` and `peek_char` past the end of the input.
+const EOF: char = '\0';
+
+const TRIPLE_QUOTE: [char; 3] = ['"', '"', '"'];
 
 pub struct Lexer {
-    input: Vec<u8>,
+    input: Vec<char>,
     position: usize,
     read_position: usize,
-    cur_char: u8,
+    cur_char: char,
+    emit_comments: bool,
+    // Symbols declared with `infix`, each read as a single `Token::Operator`
+    operators: Vec<String>,
+    // Set after `infix` so the symbol following the precedence is read whole
+    declaring_operator: bool,
 }
 
 impl Lexer {
     pub fn new(input: String) -> Lexer {
         let mut lex = Lexer {
-            input: input.into_bytes(),
+            input: input.chars().collect(),
             position: 0,
             read_position: 0,
-            cur_char: 0,
+            cur_char: EOF,
+            emit_comments: false,
+            operators: Vec::new(),
+            declaring_operator: false,
         };
         lex.read_char();
 
-        return lex;
+        lex
+    }
+
+    /// Creates a lexer that returns comments as `Token::Comment` instead of
+    /// skipping them, for tooling such as formatters and doc generators.
+    pub fn with_comments(input: String) -> Lexer {
+        let mut lex = Lexer::new(input);
+        lex.emit_comments = true;
+        lex
+    }
+
+    /// Creates a lexer that reads each of `operators` as a declared
+    /// operator, such as one an earlier REPL line declared.
+    pub fn with_operators(input: String, operators: Vec<String>) -> Lexer {
+        let mut lex = Lexer::new(input);
+        lex.operators = operators;
+        lex
+    }
+
+    /// Reads `symbol` as a declared operator from here on.
+    pub fn declare_operator(&mut self, symbol: String) {
+        if !self.operators.contains(&symbol) {
+            self.operators.push(symbol);
+        }
     }
 
     fn read_char(&mut self) {
         if self.read_position >= self.input.len() {
-            self.cur_char = 0;
+            self.cur_char = EOF;
         } else {
             self.cur_char = self.input[self.read_position];
         }
@@ -32,107 +69,547 @@ impl Lexer {
         self.read_position += 1;
     }
 
-    fn peek_char(&mut self) -> u8 {
-        if self.read_position >= self.input.len() {
-            return 0;
+    fn peek_char(&self) -> char {
+        self.char_at(self.read_position)
+    }
+
+    fn char_at(&self, position: usize) -> char {
+        if position >= self.input.len() {
+            EOF
         } else {
-            return self.input[self.read_position];
+            self.input[position]
         }
     }
 
     fn read_identifier(&mut self) -> String {
         let pos = self.position;
-        while Self::is_letter(self.cur_char) {
+        while is_xid_continue(self.cur_char) {
+            self.read_char();
+        }
+        self.input[pos..self.position].iter().collect()
+    }
+
+    fn is_operator_char(c: char) -> bool {
+        "+-*/%<>=!&|^~?@$#".contains(c)
+    }
+
+    /// Reads the symbol of an `infix` declaration, up to any comment. A
+    /// symbol that is already a built-in operator comes back as that
+    /// operator's token.
+    fn read_operator_symbol(&mut self) -> Token {
+        let pos = self.position;
+        while Self::is_operator_char(self.cur_char) && !self.is_comment_start() {
+            self.read_char();
+        }
+        let symbol: String = self.input[pos..self.position].iter().collect();
+
+        let mut lexer = Lexer::new(symbol.clone());
+        match lexer.next_token() {
+            Token::Illegal(_) | Token::Operator(_) => Token::Operator(symbol),
+            token if lexer.next_token() == Token::Eof => token,
+            _ => Token::Operator(symbol),
+        }
+    }
+
+    /// Reads the longest declared operator at the current position, if any.
+    fn read_declared_operator(&mut self) -> Option<Token> {
+        let symbol = self
+            .operators
+            .iter()
+            .filter(|symbol| {
+                let end = self.position + symbol.chars().count();
+                end <= self.input.len() && self.input[self.position..end].iter().copied().eq(symbol.chars())
+            })
+            .max_by_key(|symbol| symbol.len())?
+            .clone();
+
+        for _ in symbol.chars() {
+            self.read_char();
+        }
+        Some(Token::Operator(symbol))
+    }
+
+    fn read_number(&mut self) -> Token {
+        if self.cur_char == '0' {
+            match self.peek_char() {
+                'x' | 'X' => return self.read_radix_number(16, "hexadecimal"),
+                'o' | 'O' => return self.read_radix_number(8, "octal"),
+                'b' | 'B' => return self.read_radix_number(2, "binary"),
+                _ => {}
+            }
+        }
+
+        let pos = self.position;
+        let mut is_float = false;
+
+        let mut separated = self.read_digits();
+
+        // A fraction needs a digit after the dot, so `1..2` and `a.b` still lex
+        if self.cur_char == '.' && self.peek_char().is_ascii_digit() {
+            is_float = true;
+            self.read_char();
+            separated &= self.read_digits();
+        }
+
+        if self.cur_char == 'e' || self.cur_char == 'E' {
+            let exponent_start = match self.peek_char() {
+                '+' | '-' => self.read_position + 1,
+                _ => self.read_position,
+            };
+            if self.char_at(exponent_start).is_ascii_digit() {
+                is_float = true;
+                while self.position < exponent_start {
+                    self.read_char();
+                }
+                separated &= self.read_digits();
+            }
+        }
+
+        if !separated {
+            let literal: String = self.input[pos..self.position].iter().collect();
+            return Token::Illegal(format!("invalid decimal literal: {}", literal));
+        }
+        let literal = self.input[pos..self.position].iter().filter(|&&c| c != '_').collect::<String>();
+        if is_float {
+            Token::Float(literal.parse::<f64>().expect("float literal is well-formed"))
+        } else {
+            Self::integer_token(&literal, 10).expect("integer literal is all digits")
+        }
+    }
+
+    fn read_radix_number(&mut self, radix: u32, name: &str) -> Token {
+        // Skip the `0x`, `0o` or `0b` prefix
+        self.read_char();
+        self.read_char();
+
+        let pos = self.position;
+        while self.cur_char.is_ascii_alphanumeric() || self.cur_char == '_' {
             self.read_char();
         }
-        return String::from_utf8_lossy(&self.input[pos..self.position]).to_string();
+
+        let literal: String = self.input[pos..self.position].iter().collect();
+        // As in decimal literals, `_` may only separate two digits
+        if literal.starts_with('_') || literal.ends_with('_') || literal.contains("__") {
+            return Token::Illegal(format!("invalid {} literal: {}", name, literal));
+        }
+        Self::integer_token(&literal.replace('_', ""), radix)
+            .unwrap_or_else(|| Token::Illegal(format!("invalid {} literal: {}", name, literal)))
+    }
+
+    fn integer_token(digits: &str, radix: u32) -> Option<Token> {
+        match i64::from_str_radix(digits, radix) {
+            Ok(i) => Some(Token::Integer(i)),
+            Err(_) => BigInt::parse(digits, radix).map(Token::BigInt),
+        }
     }
 
-    fn read_number(&mut self) -> i32 {
+    /// Reads a run of digits and `_` separators, starting at a digit.
+    /// Returns `false` if a `_` doesn't sit between two digits.
+    fn read_digits(&mut self) -> bool {
         let pos = self.position;
-        while self.cur_char.is_ascii_digit() {
+        while self.cur_char.is_ascii_digit() || self.cur_char == '_' {
+            self.read_char();
+        }
+
+        let digits: String = self.input[pos..self.position].iter().collect();
+        !digits.ends_with('_') && !digits.contains("__")
+    }
+
+    fn read_string(&mut self) -> Result<Token, String> {
+        if self.input[self.position..].starts_with(&TRIPLE_QUOTE) {
+            return self.read_raw_string();
+        }
+
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut error = None;
+
+        loop {
+            self.read_char();
+            match self.cur_char {
+                '"' => break,
+                EOF => return Err("unterminated string".to_string()),
+                '\\' => {
+                    self.read_char();
+                    match self.read_escape() {
+                        Ok(c) => text.push(c),
+                        Err(e) => {
+                            error.get_or_insert(e);
+                        }
+                    }
+                }
+                '$' if self.peek_char() == '{' => {
+                    self.read_char();
+                    if !text.is_empty() {
+                        parts.push(TemplatePart::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(TemplatePart::Code(self.read_interpolation()?));
+                }
+                c => text.push(c),
+            }
+        }
+
+        if let Some(e) = error {
+            return Err(e);
+        }
+
+        if parts.is_empty() {
+            return Ok(Token::String(text));
+        }
+        if !text.is_empty() {
+            parts.push(TemplatePart::Text(text));
+        }
+        Ok(Token::Template(parts))
+    }
+
+    fn read_raw_string(&mut self) -> Result<Token, String> {
+        self.read_char();
+        self.read_char();
+        let pos = self.position + 1;
+        loop {
+            self.read_char();
+            if self.cur_char == EOF {
+                return Err("unterminated string".to_string());
+            }
+            if self.input[self.position..].starts_with(&TRIPLE_QUOTE) {
+                break;
+            }
+        }
+        let text = self.input[pos..self.position].iter().collect();
+        self.read_char();
+        self.read_char();
+        Ok(Token::String(text))
+    }
+
+    fn read_escape(&mut self) -> Result<char, String> {
+        let c = match self.cur_char {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '$' => '$',
+            'u' => return self.read_unicode_escape(),
+            EOF => return Err("unterminated string".to_string()),
+            c => return Err(format!("invalid escape sequence: \\{}", c)),
+        };
+        Ok(c)
+    }
+
+    fn read_unicode_escape(&mut self) -> Result<char, String> {
+        if self.peek_char() != '{' {
+            return Err("invalid unicode escape: expected '{' after \\u".to_string());
+        }
+        self.read_char();
+        let pos = self.position + 1;
+        while self.peek_char().is_ascii_hexdigit() {
+            self.read_char();
+        }
+        let digits = self.input[pos..self.position + 1].iter().collect::<String>();
+        if self.peek_char() != '}' {
+            return Err(format!("invalid unicode escape: \\u{{{}", digits));
+        }
+        self.read_char();
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| format!("invalid unicode escape: \\u{{{}}}", digits))
+    }
+
+    fn read_interpolation(&mut self) -> Result<String, String> {
+        let pos = self.position + 1;
+        let mut depth = 1;
+        loop {
             self.read_char();
+            match self.cur_char {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                '"' => loop {
+                    self.read_char();
+                    match self.cur_char {
+                        '"' => break,
+                        '\\' => self.read_char(),
+                        _ => {}
+                    }
+                    if self.cur_char == EOF {
+                        break;
+                    }
+                },
+                _ => {}
+            }
+            if self.cur_char == EOF {
+                return Err("unterminated string interpolation".to_string());
+            }
         }
-        return String::from_utf8_lossy(&self.input[pos..self.position])
-            .to_string()
-            .parse::<i32>()
-            .expect("Unexpected character in sequence of numbers");
+        Ok(self.input[pos..self.position].iter().collect())
     }
 
     fn skip_whitespace(&mut self) {
-        while self.cur_char.is_ascii_whitespace() {
+        while self.cur_char.is_whitespace() {
             self.read_char()
         }
     }
 
-    fn is_letter(ch: u8) -> bool {
-        return ch.is_ascii_alphabetic() || ch == b'_';
+    /// Identifiers follow Unicode XID rules, with `_` also allowed as the
+    /// first character.
+    fn is_identifier_start(ch: char) -> bool {
+        is_xid_start(ch) || ch == '_'
+    }
+
+    fn is_comment_start(&self) -> bool {
+        self.cur_char == '/' && matches!(self.peek_char(), '/' | '*')
+    }
+
+    /// Reads a `//` line comment or a nestable `/* */` block comment and
+    /// returns its full text, delimiters included.
+    fn read_comment(&mut self) -> Result<String, String> {
+        let pos = self.position;
+
+        if self.peek_char() == '/' {
+            while self.cur_char != '\n' && self.cur_char != EOF {
+                self.read_char();
+            }
+        } else {
+            let mut depth = 0;
+            loop {
+                match (self.cur_char, self.peek_char()) {
+                    (EOF, _) => return Err("unterminated block comment".to_string()),
+                    ('/', '*') => {
+                        depth += 1;
+                        self.read_char();
+                    }
+                    ('*', '/') => {
+                        depth -= 1;
+                        self.read_char();
+                    }
+                    _ => {}
+                }
+                self.read_char();
+                if depth == 0 {
+                    break;
+                }
+            }
+        }
+
+        Ok(self.input[pos..self.position].iter().collect())
     }
 
     pub fn next_token(&mut self) -> Token {
 
         self.skip_whitespace();
 
+        while self.is_comment_start() {
+            match self.read_comment() {
+                Ok(comment) if self.emit_comments => return Token::Comment(comment),
+                Ok(_) => self.skip_whitespace(),
+                Err(e) => return Token::Illegal(e),
+            }
+        }
+
+        let declaring = std::mem::take(&mut self.declaring_operator);
+        if Self::is_operator_char(self.cur_char) {
+            if declaring {
+                return self.read_operator_symbol();
+            }
+            if let Some(operator) = self.read_declared_operator() {
+                return operator;
+            }
+        }
+
         let tok = match self.cur_char {
-            b'{' => Token::LBrace,
-            b'}' => Token::RBrace,
-            b'(' => Token::LParen,
-            b')' => Token::RParen,
-            b',' => Token::Comma,
-            b';' => Token::Semicolon,
-            b'+' => Token::Plus,
-            b'-' => Token::Dash,
-            b'!' => {
-                if self.peek_char() == b'=' {
+            '{' => Token::LBrace,
+            '}' => Token::RBrace,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            ',' => Token::Comma,
+            ';' => Token::Semicolon,
+            ':' => Token::Colon,
+            '+' => {
+                if self.peek_char() == '=' {
+                    self.read_char();
+                    Token::PlusAssign
+                } else {
+                    Token::Plus
+                }
+            },
+            '-' => {
+                if self.peek_char() == '=' {
+                    self.read_char();
+                    Token::MinusAssign
+                } else {
+                    Token::Dash
+                }
+            },
+            '!' => {
+                if self.peek_char() == '=' {
                     self.read_char();
                     Token::NotEqual
                 } else {
                     Token::Bang
                 }
             },
-            b'>' => Token::GreaterThan,
-            b'<' => Token::LessThan,
-            b'*' => Token::Asterisk,
-            b'/' => Token::ForwardSlash,
-            b'=' => {
-                if self.peek_char() == b'=' {
+            '>' => match self.peek_char() {
+                '=' => {
                     self.read_char();
-                    Token::Equal
+                    Token::GreaterEqual
+                }
+                '>' => {
+                    self.read_char();
+                    Token::ShiftRight
+                }
+                _ => Token::GreaterThan,
+            },
+            '<' => match self.peek_char() {
+                '=' => {
+                    self.read_char();
+                    Token::LessEqual
+                }
+                '<' => {
+                    self.read_char();
+                    Token::ShiftLeft
+                }
+                _ => Token::LessThan,
+            },
+            '&' => {
+                if self.peek_char() == '&' {
+                    self.read_char();
+                    Token::And
+                } else {
+                    Token::Ampersand
+                }
+            },
+            '|' => match self.peek_char() {
+                '|' => {
+                    self.read_char();
+                    Token::Or
+                }
+                '>' => {
+                    self.read_char();
+                    Token::Pipeline
+                }
+                _ => Token::Pipe,
+            },
+            '^' => Token::Caret,
+            '~' => Token::Tilde,
+            '.' => match (self.peek_char(), self.char_at(self.read_position + 1)) {
+                ('.', '=') => {
+                    self.read_char();
+                    self.read_char();
+                    Token::DotDotEq
+                }
+                ('.', '.') => {
+                    self.read_char();
+                    self.read_char();
+                    Token::Ellipsis
+                }
+                ('.', _) => {
+                    self.read_char();
+                    Token::DotDot
+                }
+                _ => Token::Dot,
+            },
+            '?' => match self.peek_char() {
+                '?' => {
+                    self.read_char();
+                    Token::NullCoalesce
+                }
+                '.' => {
+                    self.read_char();
+                    Token::QuestionDot
+                }
+                _ => Token::Illegal("unexpected character: ?".to_string()),
+            },
+            '*' => {
+                if self.peek_char() == '=' {
+                    self.read_char();
+                    Token::AsteriskAssign
+                } else {
+                    Token::Asterisk
+                }
+            },
+            '%' => Token::Percent,
+            '/' => {
+                if self.peek_char() == '=' {
+                    self.read_char();
+                    Token::SlashAssign
                 } else {
-                    Token::Assign
+                    Token::ForwardSlash
+                }
+            },
+            '"' => self.read_string().unwrap_or_else(Token::Illegal),
+            '=' => match self.peek_char() {
+                '=' => {
+                    self.read_char();
+                    Token::Equal
+                }
+                '>' => {
+                    self.read_char();
+                    Token::FatArrow
                 }
+                _ => Token::Assign,
             },
-            0 => Token::Eof,
+            EOF => Token::Eof,
             c => {
-                if Self::is_letter(c) {
+                if Self::is_identifier_start(c) {
                     let id = self.read_identifier();
                     return match id.as_str() {
                         "fn" => Token::Function,
                         "let" => Token::Let,
                         "true" => Token::True,
                         "false" => Token::False,
+                        "null" => Token::Null,
                         "if" => Token::If,
                         "else" => Token::Else,
+                        "match" => Token::Match,
                         "return" => Token::Return,
+                        "while" => Token::While,
+                        "for" => Token::For,
+                        "in" => Token::In,
+                        "break" => Token::Break,
+                        "continue" => Token::Continue,
+                        "throw" => Token::Throw,
+                        "try" => Token::Try,
+                        "catch" => Token::Catch,
+                        "finally" => Token::Finally,
+                        "import" => Token::Import,
+                        "export" => Token::Export,
+                        "as" => Token::As,
+                        "struct" => Token::Struct,
+                        "enum" => Token::Enum,
+                        "is" => Token::Is,
+                        "infix" => {
+                            self.declaring_operator = true;
+                            Token::Infix
+                        }
                         _ => Token::Ident(id),
                     };
                 } else if c.is_ascii_digit() {
-                    let id = self.read_number();
-                    return Token::Integer(id);
+                    // The precedence sits between `infix` and the symbol
+                    self.declaring_operator = declaring;
+                    return self.read_number();
                 } else {
-                    Token::Illegal
+                    Token::Illegal(format!("unexpected character: {}", c))
                 }
             }
         };
 
         self.read_char();
-        return tok;
+        tok
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use anyhow::Result;
 
     #[test]
     fn test_next_token() -> Result<()> {
@@ -157,7 +634,7 @@ mod tests {
             assert_eq!(token, next_token);
         }
 
-        return Ok(());
+        Ok(())
     }
 
     #[test]
@@ -166,7 +643,7 @@ mod tests {
                      let ten = 10;\
                      let add = fn(x, y) { x + y;}; \
                      let result = add(five, ten); \
-                     !-/*5; \
+                     !-/ *5; \
                      5 < 10 > 5; \
                      if (5 < 10) { \
                         return true; \
@@ -174,7 +651,9 @@ mod tests {
                         return false; \
                      } \
                      10 == 10; \
-                     10 != 9;";
+                     10 != 9; \
+                     \"foobar\" \
+                     \"foo bar\"";
         let mut lexer = Lexer::new(input.into());
 
         let expected = vec![
@@ -252,6 +731,8 @@ mod tests {
             Token::NotEqual,
             Token::Integer(9),
             Token::Semicolon,
+            Token::String("foobar".to_string()),
+            Token::String("foo bar".to_string()),
             Token::Eof,
         ];
 
@@ -260,6 +741,694 @@ mod tests {
             println!("expected: {}, received {}", token, next_token);
             assert_eq!(token, next_token);
         }
-        return Ok(());
+        Ok(())
+    }
+
+    #[test]
+    fn test_comparison_and_logical_operators() {
+        let mut lexer = Lexer::new("a <= b >= c < d > e && f || g % h".into());
+
+        let expected = vec![
+            Token::Ident("a".to_string()),
+            Token::LessEqual,
+            Token::Ident("b".to_string()),
+            Token::GreaterEqual,
+            Token::Ident("c".to_string()),
+            Token::LessThan,
+            Token::Ident("d".to_string()),
+            Token::GreaterThan,
+            Token::Ident("e".to_string()),
+            Token::And,
+            Token::Ident("f".to_string()),
+            Token::Or,
+            Token::Ident("g".to_string()),
+            Token::Percent,
+            Token::Ident("h".to_string()),
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_null_operators() {
+        let mut lexer = Lexer::new("null ?? f?.(x) ?".into());
+
+        let expected = vec![
+            Token::Null,
+            Token::NullCoalesce,
+            Token::Ident("f".to_string()),
+            Token::QuestionDot,
+            Token::LParen,
+            Token::Ident("x".to_string()),
+            Token::RParen,
+            Token::Illegal("unexpected character: ?".to_string()),
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_assignment_operators() {
+        let mut lexer = Lexer::new("x = 1; x += 2; x -= 3; x *= 4; x /= 5; x == y".into());
+
+        let expected = vec![
+            Token::Ident("x".to_string()),
+            Token::Assign,
+            Token::Integer(1),
+            Token::Semicolon,
+            Token::Ident("x".to_string()),
+            Token::PlusAssign,
+            Token::Integer(2),
+            Token::Semicolon,
+            Token::Ident("x".to_string()),
+            Token::MinusAssign,
+            Token::Integer(3),
+            Token::Semicolon,
+            Token::Ident("x".to_string()),
+            Token::AsteriskAssign,
+            Token::Integer(4),
+            Token::Semicolon,
+            Token::Ident("x".to_string()),
+            Token::SlashAssign,
+            Token::Integer(5),
+            Token::Semicolon,
+            Token::Ident("x".to_string()),
+            Token::Equal,
+            Token::Ident("y".to_string()),
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_loop_keywords() {
+        let mut lexer = Lexer::new("while (x) { break; continue; } whiled".into());
+
+        let expected = vec![
+            Token::While,
+            Token::LParen,
+            Token::Ident("x".to_string()),
+            Token::RParen,
+            Token::LBrace,
+            Token::Break,
+            Token::Semicolon,
+            Token::Continue,
+            Token::Semicolon,
+            Token::RBrace,
+            Token::Ident("whiled".to_string()),
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_match_tokens() {
+        let mut lexer = Lexer::new("match (x) { _ => 1, y if y >= 2 => y == 3 }".into());
+
+        let expected = vec![
+            Token::Match,
+            Token::LParen,
+            Token::Ident("x".to_string()),
+            Token::RParen,
+            Token::LBrace,
+            Token::Ident("_".to_string()),
+            Token::FatArrow,
+            Token::Integer(1),
+            Token::Comma,
+            Token::Ident("y".to_string()),
+            Token::If,
+            Token::Ident("y".to_string()),
+            Token::GreaterEqual,
+            Token::Integer(2),
+            Token::FatArrow,
+            Token::Ident("y".to_string()),
+            Token::Equal,
+            Token::Integer(3),
+            Token::RBrace,
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_lambda_tokens() {
+        let mut lexer = Lexer::new("xs |> map(|x| x => x) || | >".into());
+
+        let expected = vec![
+            Token::Ident("xs".to_string()),
+            Token::Pipeline,
+            Token::Ident("map".to_string()),
+            Token::LParen,
+            Token::Pipe,
+            Token::Ident("x".to_string()),
+            Token::Pipe,
+            Token::Ident("x".to_string()),
+            Token::FatArrow,
+            Token::Ident("x".to_string()),
+            Token::RParen,
+            Token::Or,
+            Token::Pipe,
+            Token::GreaterThan,
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_error_keywords() {
+        let mut lexer = Lexer::new("try {} catch (e) {} finally {} throw e; trying".into());
+
+        let expected = vec![
+            Token::Try,
+            Token::LBrace,
+            Token::RBrace,
+            Token::Catch,
+            Token::LParen,
+            Token::Ident("e".to_string()),
+            Token::RParen,
+            Token::LBrace,
+            Token::RBrace,
+            Token::Finally,
+            Token::LBrace,
+            Token::RBrace,
+            Token::Throw,
+            Token::Ident("e".to_string()),
+            Token::Semicolon,
+            Token::Ident("trying".to_string()),
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_module_tokens() {
+        let mut lexer = Lexer::new("import \"lib/s.mk\" as s; export let x = s.y;".into());
+
+        let expected = vec![
+            Token::Import,
+            Token::String("lib/s.mk".to_string()),
+            Token::As,
+            Token::Ident("s".to_string()),
+            Token::Semicolon,
+            Token::Export,
+            Token::Let,
+            Token::Ident("x".to_string()),
+            Token::Assign,
+            Token::Ident("s".to_string()),
+            Token::Dot,
+            Token::Ident("y".to_string()),
+            Token::Semicolon,
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_struct_tokens() {
+        let mut lexer = Lexer::new("struct Point { x } Point { x: 1 }.x structs".into());
+
+        let expected = vec![
+            Token::Struct,
+            Token::Ident("Point".to_string()),
+            Token::LBrace,
+            Token::Ident("x".to_string()),
+            Token::RBrace,
+            Token::Ident("Point".to_string()),
+            Token::LBrace,
+            Token::Ident("x".to_string()),
+            Token::Colon,
+            Token::Integer(1),
+            Token::RBrace,
+            Token::Dot,
+            Token::Ident("x".to_string()),
+            Token::Ident("structs".to_string()),
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_enum_tokens() {
+        let mut lexer = Lexer::new("enum Shape { Circle(r) } s is Shape.Circle island".into());
+
+        let expected = vec![
+            Token::Enum,
+            Token::Ident("Shape".to_string()),
+            Token::LBrace,
+            Token::Ident("Circle".to_string()),
+            Token::LParen,
+            Token::Ident("r".to_string()),
+            Token::RParen,
+            Token::RBrace,
+            Token::Ident("s".to_string()),
+            Token::Is,
+            Token::Ident("Shape".to_string()),
+            Token::Dot,
+            Token::Ident("Circle".to_string()),
+            Token::Ident("island".to_string()),
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_operator_tokens() {
+        let input = "infix 6 <+> = f; infix 4 <=> = g; infix 1 + = h; infix x <+> a <+> b";
+        let mut lexer = Lexer::new(input.into());
+
+        let expected = vec![
+            Token::Infix,
+            Token::Integer(6),
+            Token::Operator("<+>".to_string()),
+            Token::Assign,
+            Token::Ident("f".to_string()),
+            Token::Semicolon,
+            Token::Infix,
+            Token::Integer(4),
+            Token::Operator("<=>".to_string()),
+            Token::Assign,
+            Token::Ident("g".to_string()),
+            Token::Semicolon,
+            Token::Infix,
+            Token::Integer(1),
+            Token::Plus,
+            Token::Assign,
+            Token::Ident("h".to_string()),
+            Token::Semicolon,
+            // Only the token right after `infix` and its precedence is a declaration
+            Token::Infix,
+            Token::Ident("x".to_string()),
+            Token::LessThan,
+            Token::Plus,
+            Token::GreaterThan,
+            // Declaring is up to the parser, so undeclared symbols split up
+            Token::Ident("a".to_string()),
+            Token::LessThan,
+            Token::Plus,
+            Token::GreaterThan,
+            Token::Ident("b".to_string()),
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_declared_operator_tokens() {
+        let input = "a <+> b <+>> c<+d";
+        let mut lexer = Lexer::with_operators(input.into(), vec!["<+>".to_string()]);
+        lexer.declare_operator("<+>>".to_string());
+
+        let expected = vec![
+            Token::Ident("a".to_string()),
+            Token::Operator("<+>".to_string()),
+            Token::Ident("b".to_string()),
+            // The longest declared symbol wins
+            Token::Operator("<+>>".to_string()),
+            Token::Ident("c".to_string()),
+            Token::LessThan,
+            Token::Plus,
+            Token::Ident("d".to_string()),
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_ranges() {
+        let mut lexer = Lexer::new("for (k, v in 0..10) 1..=n 1.5..2 a.b [...xs]".into());
+
+        let expected = vec![
+            Token::For,
+            Token::LParen,
+            Token::Ident("k".to_string()),
+            Token::Comma,
+            Token::Ident("v".to_string()),
+            Token::In,
+            Token::Integer(0),
+            Token::DotDot,
+            Token::Integer(10),
+            Token::RParen,
+            Token::Integer(1),
+            Token::DotDotEq,
+            Token::Ident("n".to_string()),
+            Token::Float(1.5),
+            Token::DotDot,
+            Token::Integer(2),
+            Token::Ident("a".to_string()),
+            Token::Dot,
+            Token::Ident("b".to_string()),
+            Token::LBracket,
+            Token::Ellipsis,
+            Token::Ident("xs".to_string()),
+            Token::RBracket,
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_numbers() {
+        let input = "5 2.75 0.5 1e3 2.5E-2 6e+1 7e x 10.foo";
+        let mut lexer = Lexer::new(input.into());
+
+        let expected = vec![
+            Token::Integer(5),
+            Token::Float(2.75),
+            Token::Float(0.5),
+            Token::Float(1000.0),
+            Token::Float(0.025),
+            Token::Float(60.0),
+            Token::Integer(7),
+            Token::Ident("e".to_string()),
+            Token::Ident("x".to_string()),
+            Token::Integer(10),
+            Token::Dot,
+            Token::Ident("foo".to_string()),
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_big_integer_literal() {
+        let mut lexer = Lexer::new("9223372036854775807 9223372036854775808;".into());
+
+        assert_eq!(Token::Integer(i64::MAX), lexer.next_token());
+        assert_eq!(
+            Token::BigInt(BigInt::parse("9223372036854775808", 10).unwrap()),
+            lexer.next_token()
+        );
+        assert_eq!(Token::Semicolon, lexer.next_token());
+    }
+
+    #[test]
+    fn test_radix_literals_and_separators() {
+        let input = "0xFF 0Xff 0b1010 0o755 1_000_000 0xdead_beef 1_000.5 0xFFFFFFFFFFFFFFFF 0b 0xZZ 0b102 \
+                     0x_FF 0b1_ 0o7__7 0b1_0 1__0 1_ 1_.5 2.5_ 1e1_";
+        let mut lexer = Lexer::new(input.into());
+
+        let expected = vec![
+            Token::Integer(255),
+            Token::Integer(255),
+            Token::Integer(10),
+            Token::Integer(493),
+            Token::Integer(1_000_000),
+            Token::Integer(0xdead_beef),
+            Token::Float(1000.5),
+            Token::BigInt(BigInt::parse("18446744073709551615", 10).unwrap()),
+            Token::Illegal("invalid binary literal: ".to_string()),
+            Token::Illegal("invalid hexadecimal literal: ZZ".to_string()),
+            Token::Illegal("invalid binary literal: 102".to_string()),
+            Token::Illegal("invalid hexadecimal literal: _FF".to_string()),
+            Token::Illegal("invalid binary literal: 1_".to_string()),
+            Token::Illegal("invalid octal literal: 7__7".to_string()),
+            Token::Integer(2),
+            Token::Illegal("invalid decimal literal: 1__0".to_string()),
+            Token::Illegal("invalid decimal literal: 1_".to_string()),
+            Token::Illegal("invalid decimal literal: 1_.5".to_string()),
+            Token::Illegal("invalid decimal literal: 2.5_".to_string()),
+            Token::Illegal("invalid decimal literal: 1e1_".to_string()),
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        let mut lexer = Lexer::new("a & b | c ^ ~d << 1 >> 2 <= >=".into());
+
+        let expected = vec![
+            Token::Ident("a".to_string()),
+            Token::Ampersand,
+            Token::Ident("b".to_string()),
+            Token::Pipe,
+            Token::Ident("c".to_string()),
+            Token::Caret,
+            Token::Tilde,
+            Token::Ident("d".to_string()),
+            Token::ShiftLeft,
+            Token::Integer(1),
+            Token::ShiftRight,
+            Token::Integer(2),
+            Token::LessEqual,
+            Token::GreaterEqual,
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_comments_are_skipped() {
+        let input = "// leading comment
+                     let x = 5; // trailing comment
+                     /* block */ x /* nested /* inner */ still comment */ + 1;
+                     10 / 2 //";
+        let mut lexer = Lexer::new(input.into());
+
+        let expected = vec![
+            Token::Let,
+            Token::Ident("x".to_string()),
+            Token::Assign,
+            Token::Integer(5),
+            Token::Semicolon,
+            Token::Ident("x".to_string()),
+            Token::Plus,
+            Token::Integer(1),
+            Token::Semicolon,
+            Token::Integer(10),
+            Token::ForwardSlash,
+            Token::Integer(2),
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_comments_as_tokens() {
+        let input = "// doc\nfn /* a /* b */ */ x";
+        let mut lexer = Lexer::with_comments(input.into());
+
+        let expected = vec![
+            Token::Comment("// doc".to_string()),
+            Token::Function,
+            Token::Comment("/* a /* b */ */".to_string()),
+            Token::Ident("x".to_string()),
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        let mut lexer = Lexer::new("1 /* open /* nested */".into());
+
+        assert_eq!(Token::Integer(1), lexer.next_token());
+        assert_eq!(
+            Token::Illegal("unterminated block comment".to_string()),
+            lexer.next_token()
+        );
+        assert_eq!(Token::Eof, lexer.next_token());
+    }
+
+    #[test]
+    fn test_unicode_identifiers() {
+        let input = "let größe = x1 + _tmp2; 名前 café\u{00A0}π ²";
+        let mut lexer = Lexer::new(input.into());
+
+        let expected = vec![
+            Token::Let,
+            Token::Ident("größe".to_string()),
+            Token::Assign,
+            Token::Ident("x1".to_string()),
+            Token::Plus,
+            Token::Ident("_tmp2".to_string()),
+            Token::Semicolon,
+            Token::Ident("名前".to_string()),
+            Token::Ident("café".to_string()),
+            Token::Ident("π".to_string()),
+            Token::Illegal("unexpected character: ²".to_string()),
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_non_ascii_strings() {
+        let mut lexer = Lexer::new("\"🐒 ${名前}!\" \"naïve\"".into());
+
+        let expected = vec![
+            Token::Template(vec![
+                TemplatePart::Text("🐒 ".to_string()),
+                TemplatePart::Code("名前".to_string()),
+                TemplatePart::Text("!".to_string()),
+            ]),
+            Token::String("naïve".to_string()),
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_brackets() {
+        let mut lexer = Lexer::new("[1, 2][0]".into());
+
+        let expected = vec![
+            Token::LBracket,
+            Token::Integer(1),
+            Token::Comma,
+            Token::Integer(2),
+            Token::RBracket,
+            Token::LBracket,
+            Token::Integer(0),
+            Token::RBracket,
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_hash_tokens() {
+        let mut lexer = Lexer::new("{\"foo\": \"bar\"}".into());
+
+        let expected = vec![
+            Token::LBrace,
+            Token::String("foo".to_string()),
+            Token::Colon,
+            Token::String("bar".to_string()),
+            Token::RBrace,
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        let mut lexer = Lexer::new("\"foo".into());
+
+        assert_eq!(Token::Illegal("unterminated string".to_string()), lexer.next_token());
+        assert_eq!(Token::Eof, lexer.next_token());
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let input = r#""a\nb\t\"c\"\\" "\u{1F412} \u{e9}" "\${x}" "\q" "\u{110000}" "ok""#;
+        let mut lexer = Lexer::new(input.into());
+
+        let expected = vec![
+            Token::String("a\nb\t\"c\"\\".to_string()),
+            Token::String("\u{1F412} é".to_string()),
+            Token::String("${x}".to_string()),
+            Token::Illegal("invalid escape sequence: \\q".to_string()),
+            Token::Illegal("invalid unicode escape: \\u{110000}".to_string()),
+            Token::String("ok".to_string()),
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
+    }
+
+    #[test]
+    fn test_raw_string() {
+        let input = "\"\"\"line one\n  \"two\" \\n ${x}\"\"\";";
+        let mut lexer = Lexer::new(input.into());
+
+        assert_eq!(
+            Token::String("line one\n  \"two\" \\n ${x}".to_string()),
+            lexer.next_token()
+        );
+        assert_eq!(Token::Semicolon, lexer.next_token());
+        assert_eq!(Token::Eof, lexer.next_token());
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        let input = r#""hello ${name}!" "${a + b}" "${f({"k": "}"})} x""#;
+        let mut lexer = Lexer::new(input.into());
+
+        let expected = vec![
+            Token::Template(vec![
+                TemplatePart::Text("hello ".to_string()),
+                TemplatePart::Code("name".to_string()),
+                TemplatePart::Text("!".to_string()),
+            ]),
+            Token::Template(vec![TemplatePart::Code("a + b".to_string())]),
+            Token::Template(vec![
+                TemplatePart::Code(r#"f({"k": "}"})"#.to_string()),
+                TemplatePart::Text(" x".to_string()),
+            ]),
+            Token::Eof,
+        ];
+
+        for token in expected {
+            assert_eq!(token, lexer.next_token());
+        }
     }
 }
@@ -1,11 +1,13 @@
-mod ast;
-mod environment;
-mod evaluator;
-mod lexer;
-mod object;
-mod parser;
-mod repl;
-mod token;
+pub mod ast;
+pub mod bigint;
+pub mod builtins;
+pub mod environment;
+pub mod evaluator;
+pub mod lexer;
+pub mod module;
+pub mod object;
+pub mod parser;
+pub mod token;
 
 use wasm_bindgen::prelude::*;
 use std::rc::Rc;
@@ -18,6 +20,28 @@ extern "C" {
     fn log(s: &str);
 }
 
+// A JavaScript object whose `load(path)` method returns a module's source,
+// or `undefined` if there is no such module
+#[wasm_bindgen]
+extern "C" {
+    pub type JsModuleLoader;
+
+    #[wasm_bindgen(method, catch)]
+    fn load(this: &JsModuleLoader, path: &str) -> Result<Option<String>, JsValue>;
+}
+
+struct HostModuleLoader(JsModuleLoader);
+
+impl module::ModuleLoader for HostModuleLoader {
+    fn load(&self, path: &str) -> Result<String, String> {
+        match self.0.load(path) {
+            Ok(Some(source)) => Ok(source),
+            Ok(None) => Err(format!("module not found: {}", path)),
+            Err(err) => Err(format!("cannot load module {}: {:?}", path, err)),
+        }
+    }
+}
+
 // Define a macro to provide `println!(..)`-style syntax
 macro_rules! console_log {
     ( $( $t:tt )* ) => {
@@ -30,29 +54,47 @@ macro_rules! console_log {
 pub fn init() {
     // Set panic hook for better error messages in the browser
     console_error_panic_hook::set_once();
+
+    // stdout isn't visible in the browser, so send `puts` to the console
+    builtins::register("puts", |args| {
+        for arg in args {
+            console_log!("{}", arg);
+        }
+        Ok(object::Object::Null)
+    });
 }
 
 // WASM-exported interpreter state
 #[wasm_bindgen]
 pub struct MonkeyInterpreter {
     env: environment::Env,
+    modules: Rc<module::Modules>,
+}
+
+impl Default for MonkeyInterpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[wasm_bindgen]
 impl MonkeyInterpreter {
     #[wasm_bindgen(constructor)]
     pub fn new() -> MonkeyInterpreter {
+        let modules = Rc::new(module::Modules::default());
         MonkeyInterpreter {
-            env: Rc::new(RefCell::new(environment::Environment::default())),
+            env: Rc::new(RefCell::new(environment::Environment::with_modules(Rc::clone(&modules)))),
+            modules,
         }
     }
 
     #[wasm_bindgen]
     pub fn eval(&self, input: &str) -> String {
-        match parser::parse(input) {
+        let operators = self.env.borrow().operators();
+        match parser::parse_with_operators(input, &operators) {
             Ok(node) => match evaluator::eval(node, &self.env) {
                 Ok(result) => format!("{}", result),
-                Err(err) => format!("Error: {}", err),
+                Err(err) => format!("{}: {}", err.kind, err),
             },
             Err(err) => format!("Parse Error: {:#?}", err),
         }
@@ -60,7 +102,14 @@ impl MonkeyInterpreter {
 
     #[wasm_bindgen]
     pub fn reset(&mut self) {
-        self.env = Rc::new(RefCell::new(environment::Environment::default()));
+        self.modules.clear_cache();
+        self.env = Rc::new(RefCell::new(environment::Environment::with_modules(Rc::clone(&self.modules))));
+    }
+
+    // Lets the page decide where `import "path"` finds module source
+    #[wasm_bindgen]
+    pub fn set_module_loader(&self, loader: JsModuleLoader) {
+        self.modules.set_loader(HostModuleLoader(loader));
     }
 }
 
@@ -1,15 +1,19 @@
-mod token;
-mod lexer;
 mod repl;
-mod ast;
-mod parser;
-mod object;
-mod evaluator;
-mod environment;
+
+use monkey_lang::module::{FileLoader, Modules};
+use std::path::PathBuf;
 
 fn main() {
+    // Imports resolve against the current directory, then MONKEY_PATH
+    let mut search_path = vec![PathBuf::from(".")];
+    if let Some(paths) = std::env::var_os("MONKEY_PATH") {
+        search_path.extend(std::env::split_paths(&paths));
+    }
+    let modules = Modules::default();
+    modules.set_loader(FileLoader::new(search_path));
+
     println!("This is the Monkey programming language!");
     println!("Feel free to type in commands");
-    repl::start();
+    repl::start(modules);
 }
 
@@ -0,0 +1,370 @@
+use crate::ast::*;
+use crate::environment::*;
+use crate::evaluator::{eval, ErrorKind, EvalError};
+use crate::object::*;
+use crate::parser::parse;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// Finds the source code of an imported module. Hosts install one with
+/// `Modules::set_loader` to decide where `import "path"` looks.
+pub trait ModuleLoader {
+    fn load(&self, path: &str) -> Result<String, String>;
+}
+
+/// Loads modules from the file system, trying each directory of the
+/// search path in order.
+pub struct FileLoader {
+    search_path: Vec<PathBuf>,
+}
+
+impl FileLoader {
+    pub fn new(search_path: Vec<PathBuf>) -> Self {
+        FileLoader { search_path }
+    }
+}
+
+impl ModuleLoader for FileLoader {
+    fn load(&self, path: &str) -> Result<String, String> {
+        for dir in &self.search_path {
+            if let Ok(source) = std::fs::read_to_string(dir.join(path)) {
+                return Ok(source);
+            }
+        }
+
+        let searched = self
+            .search_path
+            .iter()
+            .map(|dir| dir.display().to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        Err(format!("module not found: {} (searched: {})", path, searched))
+    }
+}
+
+/// The modules known to one interpreter: where to load them from, and
+/// the ones already evaluated. Every environment of the interpreter shares
+/// it, including those of the modules themselves.
+#[derive(Default)]
+pub struct Modules {
+    loader: RefCell<Option<Rc<dyn ModuleLoader>>>,
+    cache: RefCell<HashMap<String, Object>>,
+    // Paths of the modules currently being evaluated, outermost first
+    loading: RefCell<Vec<String>>,
+}
+
+impl fmt::Debug for Modules {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Modules")
+            .field("cache", &self.cache.borrow().keys())
+            .field("loading", &self.loading.borrow())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Modules {
+    /// Installs the loader used by `import`, replacing any previous one.
+    pub fn set_loader<L: ModuleLoader + 'static>(&self, loader: L) {
+        *self.loader.borrow_mut() = Some(Rc::new(loader));
+    }
+
+    /// Forgets every loaded module, so the next import evaluates it again.
+    pub fn clear_cache(&self) {
+        self.cache.borrow_mut().clear();
+    }
+}
+
+/// Returns the module at `path`, loading and evaluating it on first use
+/// with the loader of `env`'s interpreter. Each module runs once in its
+/// own environment; later imports share the cached result.
+pub fn import(path: &str, env: &Env) -> Result<Object, EvalError> {
+    let modules = Rc::clone(env.borrow().modules());
+    let path = normalize_path(path);
+    let path = path.as_str();
+
+    if let Some(module) = modules.cache.borrow().get(path).cloned() {
+        return Ok(module);
+    }
+
+    let cycle = {
+        let loading = modules.loading.borrow();
+        loading.iter().position(|p| p == path).map(|start| {
+            let mut cycle = loading[start..].to_vec();
+            cycle.push(path.to_string());
+            cycle.join(" -> ")
+        })
+    };
+    if let Some(cycle) = cycle {
+        return Err(import_error(format!("import cycle: {}", cycle)));
+    }
+
+    let loader = modules
+        .loader
+        .borrow()
+        .clone()
+        .ok_or_else(|| import_error(format!("cannot import {}: no module loader", path)))?;
+    let source = loader.load(path).map_err(import_error)?;
+
+    let program = parse(&source).map_err(|errors| {
+        let errors = errors
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<String>>()
+            .join("; ");
+        import_error(format!("cannot parse module {}: {}", path, errors))
+    })?;
+    let stmts = match &program {
+        Node::Program(stmts) => stmts.as_slice(),
+        _ => &[],
+    };
+    // The importer couldn't parse code using a module's operators, so
+    // modules don't get to declare any
+    for stmt in stmts {
+        if let Statement::Infix(_, symbol, _) = stmt {
+            return Err(import_error(format!(
+                "module {} declares operator {}: only the main program can declare operators",
+                path, symbol
+            )));
+        }
+    }
+    let names = export_names(stmts);
+
+    modules.loading.borrow_mut().push(path.to_string());
+    let env: Env = Rc::new(RefCell::new(Environment::with_modules(Rc::clone(&modules))));
+    let result = eval(program, &env);
+    modules.loading.borrow_mut().pop();
+    result?;
+
+    let exports = names
+        .into_iter()
+        .filter_map(|name| {
+            let value = env.borrow().get(&name)?;
+            Some((name, (*value).clone()))
+        })
+        .collect::<BTreeMap<String, Object>>();
+    let module = Object::Module(Rc::new(Module {
+        path: path.to_string(),
+        exports,
+    }));
+
+    modules.cache.borrow_mut().insert(path.to_string(), module.clone());
+    Ok(module)
+}
+
+/// Spells `path` the same way however it was written, so `./a.mk`,
+/// `a.mk` and `lib/../a.mk` share one cache entry.
+fn normalize_path(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." if segments.last().is_some_and(|last| *last != "..") => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+
+    let normalized = segments.join("/");
+    if path.starts_with('/') {
+        format!("/{}", normalized)
+    } else {
+        normalized
+    }
+}
+
+fn export_names(stmts: &[Statement]) -> Vec<String> {
+    stmts
+        .iter()
+        .flat_map(|stmt| match stmt {
+            Statement::Export(decl) => match decl.as_ref() {
+                Statement::Let(pattern, _) => pattern.names(),
+                Statement::Struct(decl) => vec![decl.name.clone()],
+                Statement::Enum(decl) => vec![decl.name.clone()],
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+fn import_error(msg: String) -> EvalError {
+    EvalError::with_kind(ErrorKind::ImportError, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builtins;
+    use std::cell::Cell;
+
+    struct MemoryLoader(HashMap<String, String>);
+
+    impl ModuleLoader for MemoryLoader {
+        fn load(&self, path: &str) -> Result<String, String> {
+            self.0
+                .get(path)
+                .cloned()
+                .ok_or_else(|| format!("module not found: {}", path))
+        }
+    }
+
+    /// Returns the global environment of a fresh interpreter that loads
+    /// `modules` from memory.
+    fn env_with_modules(modules: &[(&str, &str)]) -> Env {
+        let sources = modules
+            .iter()
+            .map(|(path, source)| (path.to_string(), source.to_string()))
+            .collect();
+        let modules = Modules::default();
+        modules.set_loader(MemoryLoader(sources));
+        Rc::new(RefCell::new(Environment::with_modules(Rc::new(modules))))
+    }
+
+    fn apply_test(env: &Env, test_case: &[(&str, &str)]) {
+        for (input, expected) in test_case {
+            match parse(input) {
+                Ok(node) => match eval(node, env) {
+                    Ok(evaluated) => assert_eq!(expected, &format!("{}", evaluated)),
+                    Err(err) => assert_eq!(expected, &format!("{}", err)),
+                },
+                Err(errors) => panic!("Parsing Error: {:#?}", errors),
+            }
+        }
+    }
+
+    #[test]
+    fn test_import() {
+        let env = env_with_modules(&[
+            (
+                "lib/strings.mk",
+                "let repeat = fn(s, n) { let out = \"\"; for (i in 0..n) { out += s; }; out }; \
+                 export let shout = fn(s) { repeat(s, 2) + \"!\" }; \
+                 export let [first, second] = [\"a\", \"b\"]; \
+                 export let {name} = {\"name\": \"strings\"}; \
+                 struct Pair { a, b }; export let P = Pair; \
+                 export struct Point { x, y }; export enum Shape { Dot, Circle(r) };",
+            ),
+            ("lib/uses_strings.mk", "import \"lib/strings.mk\" as s; export let loud = s.shout(\"hey\");"),
+        ]);
+
+        let test_case = [
+            ("import \"lib/strings.mk\" as s;", "module lib/strings.mk"),
+            ("s.shout(\"hi\")", "hihi!"),
+            ("[s.first, s.second, s.name]", "[a, b, strings]"),
+            ("s.repeat", "module lib/strings.mk has no export named repeat"),
+            ("s.P { a: 1, b: 2 }.b", "2"),
+            ("s.Point { x: 1, y: 2 }", "Point { x: 1, y: 2 }"),
+            ("let Shape = s.Shape; match (Shape.Circle(3)) { Shape.Circle(r) => r, _ => 0 }", "3"),
+            ("repeat", "identifier not found: repeat"),
+            ("import \"lib/uses_strings.mk\" as u; u.loud", "heyhey!"),
+            ("import \"lib/missing.mk\" as m;", "module not found: lib/missing.mk"),
+            ("try { import \"lib/missing.mk\" as m; } catch (e) { e[\"kind\"] }", "ImportError"),
+            ("let n = 5; n.x", "cannot access field x on 5"),
+        ];
+        apply_test(&env, &test_case);
+    }
+
+    #[test]
+    fn test_module_evaluated_once() {
+        let loads = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&loads);
+        builtins::register("countLoad", move |_| {
+            counter.set(counter.get() + 1);
+            Ok(Object::Null)
+        });
+        let env = env_with_modules(&[
+            ("counter.mk", "countLoad(); export let x = 1;"),
+            ("lib/uses_counter.mk", "import \"lib/../counter.mk\" as c; export let x = c.x;"),
+        ]);
+
+        let test_case = [
+            ("import \"counter.mk\" as a; import \"counter.mk\" as b; a.x + b.x", "2"),
+            ("import \"counter.mk\" as c; c.x", "1"),
+            ("import \"./counter.mk\" as d; d", "module counter.mk"),
+            ("import \"lib/uses_counter.mk\" as u; u.x", "1"),
+        ];
+        apply_test(&env, &test_case);
+        assert_eq!(1, loads.get());
+
+        // Another interpreter has its own cache, and evaluates the module again
+        let other = env_with_modules(&[("counter.mk", "countLoad(); export let x = 2;")]);
+        apply_test(&other, &[("import \"counter.mk\" as c; c.x", "2")]);
+        apply_test(&env, &[("import \"counter.mk\" as c; c.x", "1")]);
+        assert_eq!(2, loads.get());
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        let test_case = [
+            ("a.mk", "a.mk"),
+            ("./a.mk", "a.mk"),
+            ("lib//./a.mk", "lib/a.mk"),
+            ("lib/../a.mk", "a.mk"),
+            ("../a.mk", "../a.mk"),
+            ("../../lib/a.mk", "../../lib/a.mk"),
+            ("/usr/lib/./a.mk", "/usr/lib/a.mk"),
+        ];
+        for (path, expected) in test_case {
+            assert_eq!(expected, normalize_path(path));
+        }
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let env = env_with_modules(&[
+            ("a.mk", "import \"b.mk\" as b; export let x = 1;"),
+            ("b.mk", "import \"c.mk\" as c;"),
+            ("c.mk", "import \"a.mk\" as a;"),
+            ("broken.mk", "let = 1;"),
+            ("failing.mk", "export let x = 1 + true;"),
+            ("operators.mk", "infix 6 <+> = fn(a, b) { a + b }; export let x = 1 <+> 2;"),
+        ]);
+
+        let test_case = [
+            ("import \"a.mk\" as a;", "import cycle: a.mk -> b.mk -> c.mk -> a.mk"),
+            (
+                "import \"broken.mk\" as b;",
+                "cannot parse module broken.mk: Expected an identifier but got =; No prefix parse function for = is found",
+            ),
+            ("import \"failing.mk\" as f;", "type mismatch: 1 + true"),
+            (
+                "import \"operators.mk\" as o;",
+                "module operators.mk declares operator <+>: only the main program can declare operators",
+            ),
+        ];
+        apply_test(&env, &test_case);
+    }
+
+    #[test]
+    fn test_file_loader() {
+        let root = std::env::temp_dir().join(format!("monkey-modules-{}", std::process::id()));
+        let lib = root.join("lib");
+        std::fs::create_dir_all(&lib).unwrap();
+        std::fs::write(lib.join("math.mk"), "export let square = fn(x) { x * x };").unwrap();
+
+        let loader = FileLoader::new(vec![root.join("missing"), lib.clone()]);
+        assert!(loader.load("math.mk").unwrap().contains("square"));
+        assert_eq!(
+            format!(
+                "module not found: nope.mk (searched: {}, {})",
+                root.join("missing").display(),
+                lib.display()
+            ),
+            loader.load("nope.mk").unwrap_err()
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_no_loader() {
+        let env: Env = Rc::new(RefCell::new(Environment::default()));
+
+        let test_case = [("import \"x.mk\" as x;", "cannot import x.mk: no module loader")];
+        apply_test(&env, &test_case);
+    }
+}
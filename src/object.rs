@@ -1,29 +1,258 @@
 use crate::ast::*;
+use crate::bigint::BigInt;
 use crate::environment::*;
+use crate::evaluator::{ErrorKind, EvalError};
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::rc::Rc;
 
+/// The most elements a range may be collected or spread into.
+pub const MAX_COLLECT_LEN: i64 = 1 << 20;
+
 #[derive(Debug, Clone)]
 pub enum Object {
-    Integer(i32),
+    Integer(i64),
+    BigInt(BigInt),
+    Float(f64),
     Boolean(bool),
+    String(String),
+    Array(Vec<Object>),
+    Hash(BTreeMap<HashKey, Object>),
+    Range { start: i64, end: i64, inclusive: bool },
     Null,
-    ReturnValue(Rc<Object>),
-    Function(Vec<String>, BlockStatement, Env)
+    Error(Box<EvalError>),
+    Function(Vec<Parameter>, BlockStatement, Env),
+    Builtin(Builtin),
+    Module(Rc<Module>),
+    StructType(Rc<StructType>),
+    // An instance, holding a value for each of its type's fields in order
+    Struct(Rc<StructType>, Vec<Object>),
+    EnumType(Rc<EnumType>),
+    // A value of an enum type: the index of its variant and the field values
+    Variant(Rc<EnumType>, usize, Vec<Object>),
+    // The function that builds the variant at the index
+    Constructor(Rc<EnumType>, usize),
 }
 
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Object::Integer(i) => write!(f, "{}", i),
+            Object::BigInt(i) => write!(f, "{}", i),
+            Object::Float(x) => write!(f, "{:?}", x),
             Object::Boolean(b) => write!(f, "{}", b),
+            Object::String(s) => write!(f, "{}", s),
+            Object::Array(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "[{}]", elements)
+            }
+            Object::Hash(pairs) => {
+                let pairs = pairs
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key, value))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "{{{}}}", pairs)
+            }
+            Object::Range { start, end, inclusive } => {
+                write!(f, "{}{}{}", start, if *inclusive { "..=" } else { ".." }, end)
+            }
             Object::Null => write!(f, "null"),
-            Object::ReturnValue(obj) => write!(f, "{}", obj),
+            Object::Error(err) => write!(f, "{}: {}", err.kind, err.message),
             Object::Function(params, _body, _env) => {
-                let params = params.join(",");
-                write!(f, "fn({}) {{...}}", params,)
+                write!(f, "fn({}) {{...}}", format_parameters(params, ","))
             }
+            Object::Builtin(builtin) => write!(f, "builtin function {}", builtin.name),
+            Object::Module(module) => write!(f, "module {}", module.path),
+            Object::StructType(ty) => write!(f, "struct {}", ty.name),
+            Object::EnumType(ty) => write!(f, "enum {}", ty.name),
+            Object::Variant(ty, index, values) => {
+                write!(f, "{}", format_variant(&ty.variants[*index].0, values))
+            }
+            Object::Constructor(ty, index) => write!(f, "constructor {}.{}", ty.name, ty.variants[*index].0),
+            Object::Struct(ty, values) => {
+                let fields = ty
+                    .fields
+                    .iter()
+                    .zip(values)
+                    .map(|(field, value)| format!("{}: {}", field, value))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "{} {{ {} }}", ty.name, fields)
+            }
+
+        }
+    }
+}
+
+impl Object {
+    /// Wraps an integer result, keeping the `i64` fast path whenever the
+    /// value fits.
+    pub fn from_bigint(i: BigInt) -> Object {
+        match i.to_i64() {
+            Some(i) => Object::Integer(i),
+            None => Object::BigInt(i),
+        }
+    }
 
+    /// Returns the key this object hashes to, or `None` if it can't be used
+    /// as a hash key. Only integers, booleans and strings are hashable.
+    pub fn hash_key(&self) -> Option<HashKey> {
+        match self {
+            Object::Integer(i) => Some(HashKey::Integer(*i)),
+            Object::BigInt(i) => Some(HashKey::BigInt(i.clone())),
+            Object::Boolean(b) => Some(HashKey::Boolean(*b)),
+            Object::String(s) => Some(HashKey::String(s.clone())),
+            _ => None,
+        }
+    }
+
+    /// Checks that the items of a range fit in an array before `collect`
+    /// or a spread builds one. Iterating a range lazily has no such limit.
+    pub fn check_collectable(&self) -> Result<(), EvalError> {
+        match self.range_len() {
+            Some(len) if len > BigInt::from(MAX_COLLECT_LEN) => {
+                Err(EvalError::with_kind(ErrorKind::ArgumentError, format!(
+                    "range {} is too large to collect: {} elements, limit {}",
+                    self, len, MAX_COLLECT_LEN
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns how many values a range holds, or `None` if this isn't a
+    /// range. `i64::MIN..=i64::MAX` holds more than an i64 can count.
+    pub fn range_len(&self) -> Option<BigInt> {
+        let Object::Range { start, end, inclusive } = self else {
+            return None;
+        };
+        let len = &(&BigInt::from(*end) - &BigInt::from(*start)) + &BigInt::from(*inclusive as i64);
+        Some(if len.is_negative() { BigInt::from(0) } else { len })
+    }
+
+    /// Iterates over the items a single-variable `for` loop visits: array
+    /// elements, string characters, range values or hash keys. Returns
+    /// `None` if the object isn't iterable.
+    pub fn iter(&self) -> Option<Box<dyn Iterator<Item = Object> + '_>> {
+        match self {
+            Object::Hash(pairs) => Some(Box::new(pairs.keys().cloned().map(Object::from))),
+            _ => self.iter_pairs().map(|pairs| {
+                Box::new(pairs.map(|(_, value)| value)) as Box<dyn Iterator<Item = Object>>
+            }),
+        }
+    }
+
+    /// Iterates over `(key, value)` pairs: the index and element of arrays,
+    /// strings and ranges, or the key and value of hashes.
+    pub fn iter_pairs(&self) -> Option<Box<dyn Iterator<Item = (Object, Object)> + '_>> {
+        let values: Box<dyn Iterator<Item = Object>> = match self {
+            Object::Array(elements) => Box::new(elements.iter().cloned()),
+            Object::String(s) => Box::new(s.chars().map(|c| Object::String(c.to_string()))),
+            Object::Range { start, end, inclusive: false } => {
+                Box::new((*start..*end).map(Object::Integer))
+            }
+            Object::Range { start, end, inclusive: true } => {
+                Box::new((*start..=*end).map(Object::Integer))
+            }
+            Object::Hash(pairs) => {
+                return Some(Box::new(
+                    pairs.iter().map(|(key, value)| (key.clone().into(), value.clone())),
+                ))
+            }
+            _ => return None,
+        };
+        Some(Box::new(
+            values.enumerate().map(|(i, value)| (Object::Integer(i as i64), value)),
+        ))
+    }
+}
+
+pub type BuiltinFunction = dyn Fn(&[Object]) -> Result<Object, EvalError>;
+
+/// A function implemented in Rust and callable from Monkey code.
+#[derive(Clone)]
+pub struct Builtin {
+    pub name: String,
+    func: Rc<BuiltinFunction>,
+}
+
+impl Builtin {
+    pub fn new<F>(name: &str, func: F) -> Self
+    where
+        F: Fn(&[Object]) -> Result<Object, EvalError> + 'static,
+    {
+        Builtin {
+            name: name.to_string(),
+            func: Rc::new(func),
+        }
+    }
+
+    pub fn call(&self, args: &[Object]) -> Result<Object, EvalError> {
+        (self.func)(args)
+    }
+}
+
+impl fmt::Debug for Builtin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Builtin({})", self.name)
+    }
+}
+
+/// An imported module and the values it exported.
+#[derive(Debug)]
+pub struct Module {
+    pub path: String,
+    pub exports: BTreeMap<String, Object>,
+}
+
+/// A type declared with `struct`. Methods close over the environment the
+/// declaration was evaluated in.
+#[derive(Debug)]
+pub struct StructType {
+    pub name: String,
+    pub fields: Vec<String>,
+    pub methods: HashMap<String, (Vec<Parameter>, BlockStatement)>,
+    pub env: Env,
+}
+
+/// A type declared with `enum`: each variant's name and field names.
+#[derive(Debug)]
+pub struct EnumType {
+    pub name: String,
+    pub variants: Vec<(String, Vec<String>)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HashKey {
+    Integer(i64),
+    BigInt(BigInt),
+    Boolean(bool),
+    String(String),
+}
+
+impl From<HashKey> for Object {
+    fn from(key: HashKey) -> Self {
+        match key {
+            HashKey::Integer(i) => Object::Integer(i),
+            HashKey::BigInt(i) => Object::BigInt(i),
+            HashKey::Boolean(b) => Object::Boolean(b),
+            HashKey::String(s) => Object::String(s),
+        }
+    }
+}
+
+impl fmt::Display for HashKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HashKey::Integer(i) => write!(f, "{}", i),
+            HashKey::BigInt(i) => write!(f, "{}", i),
+            HashKey::Boolean(b) => write!(f, "{}", b),
+            HashKey::String(s) => write!(f, "{}", s),
         }
     }
 }
@@ -1,6 +1,7 @@
-use crate::token::Token;
+use crate::token::{TemplatePart, Token};
 use crate::lexer::Lexer;
 use crate::ast::*;
+use std::collections::HashMap;
 use std::fmt;
 
 pub struct Parser {
@@ -8,6 +9,15 @@ pub struct Parser {
     current_token: Token,
     peek_token: Token,
     errors: Vec<ParserError>,
+    // Number of enclosing loops, so `break` and `continue` can be checked
+    loop_depth: usize,
+    // Set while parsing a match guard, where `x =>` ends the guard rather
+    // than starting a lambda
+    in_match_guard: bool,
+    // Number of enclosing blocks, since exports must be at the top level
+    block_depth: usize,
+    // Precedences of the operators declared with `infix` so far
+    operators: HashMap<String, Precedence>,
 }
 
 impl Parser {
@@ -21,9 +31,21 @@ impl Parser {
             current_token,
             peek_token,
             errors,
+            loop_depth: 0,
+            in_match_guard: false,
+            block_depth: 0,
+            operators: HashMap::new(),
         }
     }
 
+    /// Creates a parser for `input` that already knows `operators`.
+    fn with_operators(input: String, operators: HashMap<String, Precedence>) -> Parser {
+        let lexer = Lexer::with_operators(input, operators.keys().cloned().collect());
+        let mut parser = Parser::new(lexer);
+        parser.operators = operators;
+        parser
+    }
+
     fn next_token(&mut self) {
         self.current_token = self.peek_token.clone();
         self.peek_token = self.lexer.next_token();
@@ -51,6 +73,13 @@ impl Parser {
         match self.current_token {
             Token::Let => self.parse_let_statement(),
             Token::Return => self.parse_return_statement(),
+            Token::Throw => self.parse_throw_statement(),
+            Token::Import => self.parse_import_statement(),
+            Token::Export => self.parse_export_statement(),
+            Token::Struct => self.parse_struct_statement(),
+            Token::Enum => self.parse_enum_statement(),
+            Token::Infix => self.parse_infix_statement(),
+            Token::Break | Token::Continue => self.parse_loop_control_statement(),
             _ => self.parse_expression_statement(),
         }
     }
@@ -60,26 +89,32 @@ impl Parser {
 
         let mut block_statement = Vec::new();
 
+        self.block_depth += 1;
+        let in_match_guard = std::mem::replace(&mut self.in_match_guard, false);
         while !self.current_token_is(&Token::RBrace) && !self.current_token_is(&Token::Eof) {
-            if let Ok(stmt) = self.parse_statement() {
-                block_statement.push(stmt);
+            match self.parse_statement() {
+                Ok(stmt) => block_statement.push(stmt),
+                Err(e) => self.errors.push(e),
             }
 
             self.next_token();
         }
+        self.in_match_guard = in_match_guard;
+        self.block_depth -= 1;
 
         Ok(block_statement)
     }
 
     fn parse_let_statement(&mut self) -> Result<Statement, ParserError> {
-        let ident = match &self.peek_token {
-            Token::Ident(id) => id.clone(),
+        let pattern = match &self.peek_token {
+            Token::Ident(_) | Token::LBracket | Token::LBrace => {
+                self.next_token();
+                self.parse_pattern()?
+            }
             t => {
                 return Err(self.error_no_identifier(t));
             }
         };
-        // Consume identifier
-        self.next_token();
 
         self.expect_peek_token(&Token::Assign)?;
         self.next_token();
@@ -90,7 +125,7 @@ impl Parser {
             self.next_token();
         }
 
-        Ok(Statement::Let(ident, expr))
+        Ok(Statement::Let(pattern, expr))
     }
 
     fn parse_return_statement(&mut self) -> Result<Statement, ParserError> {
@@ -105,6 +140,229 @@ impl Parser {
         Ok(Statement::Return(expr))
     }
 
+    fn parse_import_statement(&mut self) -> Result<Statement, ParserError> {
+        let path = match &self.peek_token {
+            Token::String(path) => path.clone(),
+            t => {
+                return Err(ParserError::new(format!("expected a module path but got {}", t)));
+            }
+        };
+        self.next_token();
+
+        self.expect_peek_token(&Token::As)?;
+        let alias = self.parse_identifier()?;
+
+        if self.peek_token_is(&Token::Semicolon) {
+            self.next_token();
+        }
+
+        Ok(Statement::Import(path, alias))
+    }
+
+    fn parse_export_statement(&mut self) -> Result<Statement, ParserError> {
+        if self.block_depth > 0 {
+            return Err(ParserError::new(
+                "exports are only allowed at the top level".to_string(),
+            ));
+        }
+
+        self.next_token();
+        let decl = match self.current_token {
+            Token::Let => self.parse_let_statement()?,
+            Token::Struct => self.parse_struct_statement()?,
+            Token::Enum => self.parse_enum_statement()?,
+            ref token => {
+                return Err(ParserError::new(format!(
+                    "expected let, struct or enum after export but got {}",
+                    token
+                )));
+            }
+        };
+        Ok(Statement::Export(Box::new(decl)))
+    }
+
+    fn parse_struct_statement(&mut self) -> Result<Statement, ParserError> {
+        let name = self.parse_identifier()?;
+        self.expect_peek_token(&Token::LBrace)?;
+
+        let mut fields: Vec<String> = Vec::new();
+        let mut methods: Vec<(String, Vec<Parameter>, BlockStatement)> = Vec::new();
+        while !self.peek_token_is(&Token::RBrace) {
+            self.next_token();
+
+            let member = match self.current_token.clone() {
+                Token::Ident(field) => {
+                    fields.push(field.clone());
+                    field
+                }
+                Token::Function => {
+                    let method = self.parse_identifier()?;
+                    self.expect_peek_token(&Token::LParen)?;
+                    let params = self.parse_fn_parameters(&Token::RParen, Precedence::Lowest)?;
+                    // The receiver is bound as `self` on every call
+                    if params.iter().any(|param| param.name() == "self") {
+                        return Err(ParserError::new(format!(
+                            "method {} in struct {} cannot declare a self parameter",
+                            method, name
+                        )));
+                    }
+                    self.expect_peek_token(&Token::LBrace)?;
+                    let body = self.parse_fn_body()?;
+                    methods.push((method.clone(), params, body));
+                    method
+                }
+                t => {
+                    return Err(ParserError::new(format!(
+                        "expected a field or method in struct {} but got {}",
+                        name, t
+                    )));
+                }
+            };
+
+            let count = fields.iter().filter(|f| **f == member).count()
+                + methods.iter().filter(|(m, _, _)| *m == member).count();
+            if count > 1 {
+                return Err(ParserError::new(format!(
+                    "duplicate member {} in struct {}",
+                    member, name
+                )));
+            }
+
+            if self.peek_token_is(&Token::Comma) || self.peek_token_is(&Token::Semicolon) {
+                self.next_token();
+            }
+        }
+        self.next_token();
+
+        if self.peek_token_is(&Token::Semicolon) {
+            self.next_token();
+        }
+
+        Ok(Statement::Struct(StructDecl { name, fields, methods }))
+    }
+
+    fn parse_enum_statement(&mut self) -> Result<Statement, ParserError> {
+        let name = self.parse_identifier()?;
+        self.expect_peek_token(&Token::LBrace)?;
+
+        let mut variants: Vec<(String, Vec<String>)> = Vec::new();
+        while !self.peek_token_is(&Token::RBrace) {
+            let variant = self.parse_identifier()?;
+            if variants.iter().any(|(v, _)| *v == variant) {
+                return Err(ParserError::new(format!(
+                    "duplicate variant {} in enum {}",
+                    variant, name
+                )));
+            }
+
+            let mut fields = Vec::new();
+            if self.peek_token_is(&Token::LParen) {
+                self.next_token();
+                while !self.peek_token_is(&Token::RParen) {
+                    fields.push(self.parse_identifier()?);
+                    if !self.peek_token_is(&Token::RParen) {
+                        self.expect_peek_token(&Token::Comma)?;
+                    }
+                }
+                self.next_token();
+            }
+            variants.push((variant, fields));
+
+            if !self.peek_token_is(&Token::RBrace) {
+                self.expect_peek_token(&Token::Comma)?;
+            }
+        }
+        self.next_token();
+
+        if self.peek_token_is(&Token::Semicolon) {
+            self.next_token();
+        }
+
+        Ok(Statement::Enum(EnumDecl { name, variants }))
+    }
+
+    fn parse_infix_statement(&mut self) -> Result<Statement, ParserError> {
+        if self.block_depth > 0 {
+            return Err(ParserError::new(
+                "operators can only be declared at the top level".to_string(),
+            ));
+        }
+
+        self.next_token();
+        let (level, precedence) = match self.current_token {
+            Token::Integer(level) => match operator_precedence(level) {
+                Some(precedence) => (level as u8, precedence),
+                None => {
+                    return Err(ParserError::new(format!(
+                        "invalid operator precedence {}: expected 0 to 8",
+                        level
+                    )));
+                }
+            },
+            ref t => {
+                return Err(ParserError::new(format!(
+                    "expected an operator precedence but got {}",
+                    t
+                )));
+            }
+        };
+
+        self.next_token();
+        let symbol = match &self.current_token {
+            Token::Operator(symbol) => symbol.clone(),
+            t => {
+                return Err(ParserError::new(format!(
+                    "expected an operator symbol but got {}",
+                    t
+                )));
+            }
+        };
+        check_operator_symbol(&symbol)?;
+        // Declared before the right-hand side is read, so the rest of the
+        // program is lexed and parsed with the operator in place
+        self.lexer.declare_operator(symbol.clone());
+        self.operators.insert(symbol.clone(), precedence);
+
+        self.expect_peek_token(&Token::Assign)?;
+        self.next_token();
+        let expr = self.parse_expression(Precedence::Lowest)?;
+
+        if self.peek_token_is(&Token::Semicolon) {
+            self.next_token();
+        }
+
+        Ok(Statement::Infix(level, symbol, expr))
+    }
+
+    fn parse_throw_statement(&mut self) -> Result<Statement, ParserError> {
+        self.next_token();
+
+        let expr = self.parse_expression(Precedence::Lowest)?;
+
+        if self.peek_token_is(&Token::Semicolon) {
+            self.next_token();
+        }
+
+        Ok(Statement::Throw(expr))
+    }
+
+    fn parse_loop_control_statement(&mut self) -> Result<Statement, ParserError> {
+        let (stmt, keyword) = match self.current_token {
+            Token::Break => (Statement::Break, "break"),
+            _ => (Statement::Continue, "continue"),
+        };
+
+        if self.loop_depth == 0 {
+            return Err(ParserError::new(format!("{} outside of a loop", keyword)));
+        }
+
+        if self.peek_token_is(&Token::Semicolon) {
+            self.next_token();
+        }
+
+        Ok(stmt)
+    }
+
     fn parse_expression_statement(&mut self) -> Result<Statement, ParserError> {
         let expr = self.parse_expression(Precedence::Lowest)?;
 
@@ -117,18 +375,43 @@ impl Parser {
 
     fn parse_expression(&mut self, precedence: Precedence) -> Result<Expression, ParserError> {
         let mut left_expr = match self.current_token {
+            Token::Ident(_) if self.peek_token_is(&Token::FatArrow) && !self.in_match_guard => {
+                self.parse_arrow_lambda()
+            }
             Token::Ident(ref id) => Ok(Expression::Ident(id.clone())),
             Token::Integer(i) => Ok(Expression::Lit(Literal::Integer(i))),
+            Token::BigInt(ref i) => Ok(Expression::Lit(Literal::BigInt(i.clone()))),
+            Token::Float(x) => Ok(Expression::Lit(Literal::Float(x))),
+            Token::String(ref s) => Ok(Expression::Lit(Literal::String(s.clone()))),
+            Token::Template(ref parts) => parse_template(parts, &self.operators),
+            Token::Illegal(ref msg) => return Err(ParserError::new(msg.clone())),
             Token::True => Ok(Expression::Lit(Literal::Boolean(true))),
             Token::False => Ok(Expression::Lit(Literal::Boolean(false))),
-            Token::Bang | Token::Dash => self.parse_prefix_expression(),
+            Token::Null => Ok(Expression::Lit(Literal::Null)),
+            Token::Bang | Token::Dash | Token::Tilde => self.parse_prefix_expression(),
+            Token::Pipe | Token::Or => self.parse_pipe_lambda(),
             Token::LParen => {
                 self.next_token();
-                let expr = self.parse_expression(Precedence::Lowest);
+                let expr = self.without_match_guard(|p| p.parse_expression(Precedence::Lowest))?;
                 self.expect_peek_token(&Token::RParen)?;
-                expr
+                if is_optional_chain(&expr) {
+                    Ok(Expression::Grouped(Box::new(expr)))
+                } else {
+                    Ok(expr)
+                }
+            },
+            Token::LBracket => {
+                let elements = self.parse_expression_list(&Token::RBracket)?;
+                Ok(Expression::Array(elements))
             },
+            // Blocks are only parsed after keywords and match arrows, so a brace in
+            // expression position always starts a hash literal.
+            Token::LBrace => self.without_match_guard(Self::parse_hash_literal),
             Token::If => self.parse_if_expression(),
+            Token::Match => self.parse_match_expression(),
+            Token::Try => self.parse_try_expression(),
+            Token::While => self.parse_while_expression(),
+            Token::For => self.parse_for_expression(),
             Token::Function => self.parse_fn_expression(),
             _ => {
                 return Err(ParserError::new(format!(
@@ -144,19 +427,71 @@ impl Parser {
                 | Token::Dash
                 | Token::Asterisk
                 | Token::ForwardSlash
+                | Token::Percent
                 | Token::Equal
                 | Token::NotEqual
                 | Token::LessThan
-                | Token::GreaterThan => {
+                | Token::GreaterThan
+                | Token::LessEqual
+                | Token::GreaterEqual
+                | Token::And
+                | Token::Or
+                | Token::Ampersand
+                | Token::Pipe
+                | Token::Caret
+                | Token::ShiftLeft
+                | Token::ShiftRight
+                | Token::NullCoalesce
+                | Token::Is
+                | Token::Operator(_)
+                | Token::DotDot
+                | Token::DotDotEq => {
                     self.next_token();
-                    let expr = left_expr.unwrap();
+                    let expr = left_expr?;
                     left_expr = self.parse_infix_expression(expr);
                 }
                 Token::LParen => {
                     self.next_token();
-                    let expr = left_expr.unwrap();
+                    let expr = left_expr?;
                     left_expr = self.parse_fn_call_expression(expr);
                 }
+                // `Name { ... }` or `module.Name { ... }` builds a struct;
+                // conditions are parenthesized, so a name is never followed
+                // by a block
+                Token::LBrace if matches!(left_expr, Ok(Expression::Ident(_) | Expression::Member(..))) => {
+                    self.next_token();
+                    let expr = left_expr?;
+                    left_expr = self.without_match_guard(|p| p.parse_struct_literal(expr));
+                }
+                Token::Dot => {
+                    self.next_token();
+                    let expr = left_expr?;
+                    left_expr = self.parse_member_expression(expr);
+                }
+                Token::Pipeline => {
+                    self.next_token();
+                    let expr = left_expr?;
+                    left_expr = self.parse_pipeline_expression(expr);
+                }
+                Token::Assign
+                | Token::PlusAssign
+                | Token::MinusAssign
+                | Token::AsteriskAssign
+                | Token::SlashAssign => {
+                    self.next_token();
+                    let expr = left_expr?;
+                    left_expr = self.parse_assign_expression(expr);
+                }
+                Token::QuestionDot => {
+                    self.next_token();
+                    let expr = left_expr?;
+                    left_expr = self.parse_optional_call_expression(expr);
+                }
+                Token::LBracket => {
+                    self.next_token();
+                    let expr = left_expr?;
+                    left_expr = self.parse_index_expression(expr);
+                }
                 _ => return left_expr,
             }
         }
@@ -176,7 +511,7 @@ impl Parser {
     fn parse_infix_expression(&mut self, left_expr: Expression) -> Result<Expression, ParserError> {
         let infix_op = self.current_token.clone();
 
-        let precedence = token_to_precedence(&self.current_token);
+        let precedence = self.token_precedence(&self.current_token);
         self.next_token();
 
         let right_expr = self.parse_expression(precedence)?;
@@ -188,6 +523,90 @@ impl Parser {
         ))
     }
 
+    /// Desugars `left |> f(args)` into `f(left, args)`, and `left |> f`
+    /// into `f(left)`. In a chain like `left |> f(a)()` the value goes to
+    /// the first call, giving `f(left, a)()`.
+    fn parse_pipeline_expression(&mut self, left_expr: Expression) -> Result<Expression, ParserError> {
+        self.next_token();
+
+        let right_expr = self.parse_expression(Precedence::Pipeline)?;
+
+        Ok(match right_expr {
+            Expression::FunctionCall(..) | Expression::OptionalCall(..) => {
+                insert_pipeline_argument(right_expr, left_expr)
+            }
+            func => Expression::FunctionCall(Box::new(func), vec![left_expr]),
+        })
+    }
+
+    fn parse_struct_literal(&mut self, ty: Expression) -> Result<Expression, ParserError> {
+        let mut fields = Vec::new();
+        while !self.peek_token_is(&Token::RBrace) {
+            let field = self.parse_identifier()?;
+
+            // `Point { x }` is shorthand for `Point { x: x }`
+            let value = if self.peek_token_is(&Token::Colon) {
+                self.next_token();
+                self.next_token();
+                self.parse_expression(Precedence::Lowest)?
+            } else {
+                Expression::Ident(field.clone())
+            };
+            fields.push((field, value));
+
+            if !self.peek_token_is(&Token::RBrace) {
+                self.expect_peek_token(&Token::Comma)?;
+            }
+        }
+        self.next_token();
+
+        Ok(Expression::StructLiteral(Box::new(ty), fields))
+    }
+
+    fn parse_hash_literal(&mut self) -> Result<Expression, ParserError> {
+        let mut pairs = Vec::new();
+
+        while !self.peek_token_is(&Token::RBrace) {
+            self.next_token();
+            let key = self.parse_expression(Precedence::Lowest)?;
+
+            self.expect_peek_token(&Token::Colon)?;
+            self.next_token();
+
+            let value = self.parse_expression(Precedence::Lowest)?;
+            pairs.push((key, value));
+
+            if !self.peek_token_is(&Token::RBrace) {
+                self.expect_peek_token(&Token::Comma)?;
+            }
+        }
+
+        self.expect_peek_token(&Token::RBrace)?;
+
+        Ok(Expression::Hash(pairs))
+    }
+
+    fn parse_assign_expression(&mut self, target: Expression) -> Result<Expression, ParserError> {
+        let op = self.current_token.clone();
+
+        let id = match target {
+            Expression::Ident(id) => id,
+            target => {
+                return Err(ParserError::new(format!(
+                    "invalid assignment target: {}",
+                    target
+                )));
+            }
+        };
+
+        self.next_token();
+
+        // Assignment is right-associative, so `a = b = 1` assigns to `b` first
+        let value = self.parse_expression(Precedence::Lowest)?;
+
+        Ok(Expression::Assign(op, id, Box::new(value)))
+    }
+
     fn parse_if_expression(&mut self) -> Result<Expression, ParserError> {
         self.expect_peek_token(&Token::LParen)?;
         self.next_token();
@@ -202,8 +621,14 @@ impl Parser {
         let alternative = if self.peek_token_is(&Token::Else) {
             self.next_token();
 
-            self.expect_peek_token(&Token::LBrace)?;
-            Some(self.parse_block_statement()?)
+            // `else if` is sugar for an else block holding just the nested if
+            if self.peek_token_is(&Token::If) {
+                self.next_token();
+                Some(vec![Statement::Expr(self.parse_if_expression()?)])
+            } else {
+                self.expect_peek_token(&Token::LBrace)?;
+                Some(self.parse_block_statement()?)
+            }
         } else {
             None
         };
@@ -215,76 +640,456 @@ impl Parser {
         ))
     }
 
+    fn parse_try_expression(&mut self) -> Result<Expression, ParserError> {
+        self.expect_peek_token(&Token::LBrace)?;
+        let body = self.parse_block_statement()?;
+
+        let catch = if self.peek_token_is(&Token::Catch) {
+            self.next_token();
+
+            // The error binding is optional: `catch { ... }`
+            let param = if self.peek_token_is(&Token::LParen) {
+                self.next_token();
+                let param = self.parse_identifier()?;
+                self.expect_peek_token(&Token::RParen)?;
+                Some(param)
+            } else {
+                None
+            };
+
+            self.expect_peek_token(&Token::LBrace)?;
+            Some((param, self.parse_block_statement()?))
+        } else {
+            None
+        };
+
+        let finally = if self.peek_token_is(&Token::Finally) {
+            self.next_token();
+            self.expect_peek_token(&Token::LBrace)?;
+            Some(self.parse_block_statement()?)
+        } else {
+            None
+        };
+
+        if catch.is_none() && finally.is_none() {
+            return Err(ParserError::new(
+                "expected catch or finally after try block".to_string(),
+            ));
+        }
+
+        Ok(Expression::Try(body, catch, finally))
+    }
+
+    fn parse_match_expression(&mut self) -> Result<Expression, ParserError> {
+        self.expect_peek_token(&Token::LParen)?;
+        self.next_token();
+
+        let subject = self.parse_expression(Precedence::Lowest)?;
+
+        self.expect_peek_token(&Token::RParen)?;
+        self.expect_peek_token(&Token::LBrace)?;
+
+        let mut arms = Vec::new();
+        while !self.peek_token_is(&Token::RBrace) {
+            self.next_token();
+            arms.push(self.parse_match_arm()?);
+
+            if self.peek_token_is(&Token::Comma) {
+                self.next_token();
+            }
+        }
+        self.next_token();
+
+        Ok(Expression::Match(Box::new(subject), arms))
+    }
+
+    /// Runs `parse` with the match-guard restrictions lifted, for brackets
+    /// nested inside a guard where `x => ...` can't be mistaken for the arm.
+    fn without_match_guard<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParserError>,
+    ) -> Result<T, ParserError> {
+        let in_match_guard = std::mem::replace(&mut self.in_match_guard, false);
+        let result = parse(self);
+        self.in_match_guard = in_match_guard;
+        result
+    }
+
+    fn parse_match_arm(&mut self) -> Result<MatchArm, ParserError> {
+        let pattern = self.parse_pattern()?;
+
+        let guard = if self.peek_token_is(&Token::If) {
+            self.next_token();
+            self.next_token();
+            let in_match_guard = std::mem::replace(&mut self.in_match_guard, true);
+            let guard = self.parse_expression(Precedence::Lowest);
+            self.in_match_guard = in_match_guard;
+            Some(guard?)
+        } else {
+            None
+        };
+
+        self.expect_peek_token(&Token::FatArrow)?;
+
+        // An arm body is either a block or a single expression
+        let body = if self.peek_token_is(&Token::LBrace) {
+            self.next_token();
+            self.parse_block_statement()?
+        } else {
+            self.next_token();
+            vec![Statement::Expr(self.parse_expression(Precedence::Lowest)?)]
+        };
+
+        Ok(MatchArm { pattern, guard, body })
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern, ParserError> {
+        match self.current_token {
+            Token::Ident(ref id) if id == "_" => Ok(Pattern::Wildcard),
+            Token::Ident(ref enum_name) if self.peek_token_is(&Token::Dot) => {
+                let enum_name = enum_name.clone();
+                self.next_token();
+                let variant = self.parse_identifier()?;
+
+                let mut fields = Vec::new();
+                if self.peek_token_is(&Token::LParen) {
+                    self.next_token();
+                    while !self.peek_token_is(&Token::RParen) {
+                        self.next_token();
+                        fields.push(self.parse_pattern()?);
+                        if !self.peek_token_is(&Token::RParen) {
+                            self.expect_peek_token(&Token::Comma)?;
+                        }
+                    }
+                    self.next_token();
+                }
+                Ok(Pattern::Variant(enum_name, variant, fields))
+            }
+            Token::Ident(ref id) => Ok(Pattern::Binding(id.clone())),
+            Token::LBracket => {
+                let mut elements = Vec::new();
+                let mut rest = None;
+                while !self.peek_token_is(&Token::RBracket) {
+                    self.next_token();
+                    if self.current_token_is(&Token::Ellipsis) {
+                        self.next_token();
+                        rest = match self.parse_pattern()? {
+                            pattern @ (Pattern::Binding(_) | Pattern::Wildcard) => Some(Box::new(pattern)),
+                            pattern => {
+                                return Err(ParserError::new(format!("invalid rest pattern: ...{}", pattern)));
+                            }
+                        };
+                        if !self.peek_token_is(&Token::RBracket) {
+                            return Err(ParserError::new(
+                                "a rest pattern must be the last element".to_string(),
+                            ));
+                        }
+                        break;
+                    }
+                    elements.push(self.parse_pattern()?);
+                    if !self.peek_token_is(&Token::RBracket) {
+                        self.expect_peek_token(&Token::Comma)?;
+                    }
+                }
+                self.next_token();
+                Ok(Pattern::Array(elements, rest))
+            }
+            Token::LBrace => {
+                let mut pairs = Vec::new();
+                while !self.peek_token_is(&Token::RBrace) {
+                    self.next_token();
+
+                    let (key, pattern) = match self.current_token.clone() {
+                        // `{name}` is shorthand for `{"name": name}`, and `{_}` for `{"_": _}`
+                        Token::Ident(id) if !self.peek_token_is(&Token::Colon) => {
+                            let pattern = match id.as_str() {
+                                "_" => Pattern::Wildcard,
+                                _ => Pattern::Binding(id.clone()),
+                            };
+                            (Literal::String(id), pattern)
+                        }
+                        _ => {
+                            let key = match self.parse_pattern()? {
+                                Pattern::Lit(key) if !matches!(key, Literal::Float(_) | Literal::Null) => key,
+                                key => {
+                                    return Err(ParserError::new(format!("invalid hash pattern key: {}", key)));
+                                }
+                            };
+                            self.expect_peek_token(&Token::Colon)?;
+                            self.next_token();
+                            (key, self.parse_pattern()?)
+                        }
+                    };
+                    pairs.push((key, pattern));
+                    if !self.peek_token_is(&Token::RBrace) {
+                        self.expect_peek_token(&Token::Comma)?;
+                    }
+                }
+                self.next_token();
+                Ok(Pattern::Hash(pairs))
+            }
+            Token::Dash => {
+                self.next_token();
+                match self.current_token {
+                    Token::Integer(i) => Ok(Pattern::Lit(Literal::Integer(-i))),
+                    Token::BigInt(ref i) => Ok(Pattern::Lit(Literal::BigInt(-i))),
+                    Token::Float(x) => Ok(Pattern::Lit(Literal::Float(-x))),
+                    ref token => Err(ParserError::new(format!("expected a number after - in pattern but got {}", token))),
+                }
+            }
+            Token::Integer(i) => Ok(Pattern::Lit(Literal::Integer(i))),
+            Token::BigInt(ref i) => Ok(Pattern::Lit(Literal::BigInt(i.clone()))),
+            Token::Float(x) => Ok(Pattern::Lit(Literal::Float(x))),
+            Token::String(ref s) => Ok(Pattern::Lit(Literal::String(s.clone()))),
+            Token::True => Ok(Pattern::Lit(Literal::Boolean(true))),
+            Token::False => Ok(Pattern::Lit(Literal::Boolean(false))),
+            Token::Null => Ok(Pattern::Lit(Literal::Null)),
+            ref token => Err(ParserError::new(format!("expected a pattern but got {}", token))),
+        }
+    }
+
+    fn parse_while_expression(&mut self) -> Result<Expression, ParserError> {
+        self.expect_peek_token(&Token::LParen)?;
+        self.next_token();
+
+        let condition = self.parse_expression(Precedence::Lowest)?;
+
+        self.expect_peek_token(&Token::RParen)?;
+        self.expect_peek_token(&Token::LBrace)?;
+
+        let body = self.parse_loop_body()?;
+
+        Ok(Expression::While(Box::new(condition), body))
+    }
+
+    fn parse_for_expression(&mut self) -> Result<Expression, ParserError> {
+        self.expect_peek_token(&Token::LParen)?;
+
+        // Either `for (x in ...)` or `for (key, value in ...)`
+        let mut variables = vec![self.parse_identifier()?];
+        if self.peek_token_is(&Token::Comma) {
+            self.next_token();
+            variables.push(self.parse_identifier()?);
+        }
+
+        self.expect_peek_token(&Token::In)?;
+        self.next_token();
+
+        let iterable = self.parse_expression(Precedence::Lowest)?;
+
+        self.expect_peek_token(&Token::RParen)?;
+        self.expect_peek_token(&Token::LBrace)?;
+
+        let body = self.parse_loop_body()?;
+
+        Ok(Expression::For(variables, Box::new(iterable), body))
+    }
+
+    fn parse_identifier(&mut self) -> Result<String, ParserError> {
+        self.next_token();
+        match &self.current_token {
+            Token::Ident(id) => Ok(id.clone()),
+            token => Err(self.error_no_identifier(token)),
+        }
+    }
+
+    fn parse_loop_body(&mut self) -> Result<BlockStatement, ParserError> {
+        self.loop_depth += 1;
+        let body = self.parse_block_statement();
+        self.loop_depth -= 1;
+        body
+    }
+
     fn parse_fn_expression(&mut self) -> Result<Expression, ParserError> {
         self.expect_peek_token(&Token::LParen)?;
 
-        let parameters = self.parse_fn_parameters()?;
+        let parameters = self.parse_fn_parameters(&Token::RParen, Precedence::Lowest)?;
 
         self.expect_peek_token(&Token::LBrace)?;
 
-        let body = self.parse_block_statement()?;
+        let body = self.parse_fn_body()?;
+
+        Ok(Expression::Function(parameters, body))
+    }
+
+    fn parse_fn_body(&mut self) -> Result<BlockStatement, ParserError> {
+        // A loop outside the function can't be broken out of from inside it
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let body = self.parse_block_statement();
+        self.loop_depth = loop_depth;
+        body
+    }
+
+    /// Parses `|x, y| body`, or `|| body` with no parameters.
+    fn parse_pipe_lambda(&mut self) -> Result<Expression, ParserError> {
+        let parameters = if self.current_token_is(&Token::Or) {
+            Vec::new()
+        } else {
+            // Defaults bind tighter than `|` so the closing pipe ends them
+            self.parse_fn_parameters(&Token::Pipe, Precedence::BitOr)?
+        };
+
+        let body = self.parse_lambda_body()?;
+
+        Ok(Expression::Function(parameters, body))
+    }
+
+    /// Parses `x => body`.
+    fn parse_arrow_lambda(&mut self) -> Result<Expression, ParserError> {
+        let parameter = match &self.current_token {
+            Token::Ident(id) => Parameter::Required(id.clone()),
+            token => return Err(self.error_no_identifier(token)),
+        };
+        self.next_token();
+
+        let body = self.parse_lambda_body()?;
+
+        Ok(Expression::Function(vec![parameter], body))
+    }
+
+    /// Parses a lambda body, which is either a block or a single expression.
+    fn parse_lambda_body(&mut self) -> Result<BlockStatement, ParserError> {
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        self.next_token();
+        let body = if self.current_token_is(&Token::LBrace) {
+            self.parse_block_statement()
+        } else {
+            self.parse_expression(Precedence::Lowest)
+                .map(|expr| vec![Statement::Expr(expr)])
+        };
+        self.loop_depth = loop_depth;
+        body
+    }
+
+    fn parse_fn_parameters(
+        &mut self,
+        end: &Token,
+        default_precedence: Precedence,
+    ) -> Result<Vec<Parameter>, ParserError> {
+        // Defaults are evaluated when the function is called, outside any loop around it
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let parameters = self.parse_fn_parameter_list(end, default_precedence);
+        self.loop_depth = loop_depth;
+        parameters
+    }
+
+    fn parse_fn_parameter_list(
+        &mut self,
+        end: &Token,
+        default_precedence: Precedence,
+    ) -> Result<Vec<Parameter>, ParserError> {
+        let mut parameters = Vec::new();
+
+        // No params
+        if self.peek_token_is(end) {
+            self.next_token();
+            return Ok(parameters);
+        }
+
+        self.next_token();
+        parameters.push(self.parse_fn_parameter(default_precedence)?);
+
+        while self.peek_token_is(&Token::Comma) {
+            if let Some(Parameter::Rest(_)) = parameters.last() {
+                return Err(ParserError::new("a rest parameter must be the last parameter".to_string()));
+            }
+            self.next_token();
+            self.next_token();
+            parameters.push(self.parse_fn_parameter(default_precedence)?);
+        }
+
+        self.expect_peek_token(end)?;
 
-        Ok(Expression::Function(parameters, body))
+        Ok(parameters)
     }
 
-    fn parse_fn_parameters(&mut self) -> Result<Vec<String>, ParserError> {
-        let mut identifiers = Vec::new();
-
-        // No params
-        if self.peek_token_is(&Token::RParen) {
+    fn parse_fn_parameter(&mut self, default_precedence: Precedence) -> Result<Parameter, ParserError> {
+        let is_rest = self.current_token_is(&Token::Ellipsis);
+        if is_rest {
             self.next_token();
-            return Ok(identifiers);
         }
 
-        self.next_token();
-
-        match &self.current_token {
-            Token::Ident(id) => identifiers.push(id.clone()),
+        let name = match &self.current_token {
+            Token::Ident(id) => id.clone(),
             token => return Err(self.error_no_identifier(token)),
-        }
+        };
 
-        while self.peek_token_is(&Token::Comma) {
+        if is_rest {
+            Ok(Parameter::Rest(name))
+        } else if self.peek_token_is(&Token::Assign) {
             self.next_token();
             self.next_token();
-            match &self.current_token {
-                Token::Ident(id) => identifiers.push(id.clone()),
-                token => return Err(self.error_no_identifier(token)),
-            }
+            Ok(Parameter::Optional(name, self.parse_expression(default_precedence)?))
+        } else {
+            Ok(Parameter::Required(name))
         }
-
-        self.expect_peek_token(&Token::RParen)?;
-
-        Ok(identifiers)
     }
 
     fn parse_fn_call_expression(&mut self, expr: Expression) -> Result<Expression, ParserError> {
-        let arguments = self.parse_call_arguments()?;
+        let arguments = self.parse_expression_list(&Token::RParen)?;
         Ok(Expression::FunctionCall(Box::new(expr), arguments))
     }
 
-    fn parse_call_arguments(&mut self) -> Result<Vec<Expression>, ParserError> {
+    fn parse_optional_call_expression(&mut self, expr: Expression) -> Result<Expression, ParserError> {
+        self.expect_peek_token(&Token::LParen)?;
+        let arguments = self.parse_expression_list(&Token::RParen)?;
+        Ok(Expression::OptionalCall(Box::new(expr), arguments))
+    }
+
+    fn parse_member_expression(&mut self, expr: Expression) -> Result<Expression, ParserError> {
+        let name = self.parse_identifier()?;
+        Ok(Expression::Member(Box::new(expr), name))
+    }
+
+    fn parse_index_expression(&mut self, expr: Expression) -> Result<Expression, ParserError> {
+        self.next_token();
+
+        let index = self.without_match_guard(|p| p.parse_expression(Precedence::Lowest))?;
+
+        self.expect_peek_token(&Token::RBracket)?;
+
+        Ok(Expression::Index(Box::new(expr), Box::new(index)))
+    }
+
+    fn parse_expression_list(&mut self, end: &Token) -> Result<Vec<Expression>, ParserError> {
+        self.without_match_guard(|p| p.parse_expression_list_items(end))
+    }
+
+    fn parse_expression_list_items(&mut self, end: &Token) -> Result<Vec<Expression>, ParserError> {
         let mut list = Vec::new();
 
-        if self.peek_token_is(&Token::RParen) {
+        if self.peek_token_is(end) {
             self.next_token();
             return Ok(list);
         }
 
         self.next_token();
 
-        list.push(self.parse_expression(Precedence::Lowest)?);
+        list.push(self.parse_list_element()?);
 
         while self.peek_token_is(&Token::Comma) {
             self.next_token();
             self.next_token();
-            list.push(self.parse_expression(Precedence::Lowest)?);
+            list.push(self.parse_list_element()?);
         }
 
-        self.expect_peek_token(&Token::RParen)?;
+        self.expect_peek_token(end)?;
 
         Ok(list)
     }
 
+    /// Parses an element of an argument or array list, which may be a
+    /// `...spread` of another collection.
+    fn parse_list_element(&mut self) -> Result<Expression, ParserError> {
+        if self.current_token_is(&Token::Ellipsis) {
+            self.next_token();
+            let expr = self.parse_expression(Precedence::Lowest)?;
+            return Ok(Expression::Spread(Box::new(expr)));
+        }
+
+        self.parse_expression(Precedence::Lowest)
+    }
+
     fn current_token_is(&self, token: &Token) -> bool {
         self.current_token == *token
     }
@@ -294,11 +1099,18 @@ impl Parser {
     }
 
     fn next_token_precedence(&self) -> Precedence {
-        token_to_precedence(&self.peek_token)
+        self.token_precedence(&self.peek_token)
+    }
+
+    fn token_precedence(&self, token: &Token) -> Precedence {
+        match token {
+            Token::Operator(symbol) => self.operators.get(symbol).copied().unwrap_or(Precedence::Lowest),
+            token => token_to_precedence(token),
+        }
     }
 
     fn expect_peek_token(&mut self, token: &Token) -> Result<(), ParserError> {
-        if self.peek_token_is(&token) {
+        if self.peek_token_is(token) {
             self.next_token();
             Ok(())
         } else {
@@ -314,9 +1126,83 @@ impl Parser {
     }
 }
 
+/// Whether `expr` is a chain of calls, indexes and member accesses with a
+/// `?.()` in it, which parentheses around it cut short.
+fn is_optional_chain(expr: &Expression) -> bool {
+    match expr {
+        Expression::OptionalCall(..) => true,
+        Expression::FunctionCall(inner, _) | Expression::Index(inner, _) | Expression::Member(inner, _) => {
+            is_optional_chain(inner)
+        }
+        _ => false,
+    }
+}
+
+/// Inserts `arg` as the first argument of the innermost call in a chain
+/// of calls, so `f(a)()` becomes `f(arg, a)()`.
+fn insert_pipeline_argument(expr: Expression, arg: Expression) -> Expression {
+    match expr {
+        Expression::FunctionCall(func, args) if is_call(&func) => {
+            Expression::FunctionCall(Box::new(insert_pipeline_argument(*func, arg)), args)
+        }
+        Expression::OptionalCall(func, args) if is_call(&func) => {
+            Expression::OptionalCall(Box::new(insert_pipeline_argument(*func, arg)), args)
+        }
+        Expression::FunctionCall(func, mut args) => {
+            args.insert(0, arg);
+            Expression::FunctionCall(func, args)
+        }
+        Expression::OptionalCall(func, mut args) => {
+            args.insert(0, arg);
+            Expression::OptionalCall(func, args)
+        }
+        expr => expr,
+    }
+}
+
+fn is_call(expr: &Expression) -> bool {
+    matches!(expr, Expression::FunctionCall(..) | Expression::OptionalCall(..))
+}
+
+fn parse_template(
+    parts: &[TemplatePart],
+    operators: &HashMap<String, Precedence>,
+) -> Result<Expression, ParserError> {
+    let mut segments = Vec::new();
+
+    for part in parts {
+        match part {
+            TemplatePart::Text(text) => segments.push(TemplateSegment::Text(text.clone())),
+            TemplatePart::Code(code) => {
+                let mut parser = Parser::with_operators(code.clone(), operators.clone());
+                let expr = parser.parse_expression(Precedence::Lowest)?;
+                if !parser.peek_token_is(&Token::Eof) {
+                    return Err(ParserError::new(format!(
+                        "expected end of interpolation in \"${{{}}}\", but got {} instead",
+                        code, parser.peek_token
+                    )));
+                }
+                segments.push(TemplateSegment::Expr(expr));
+            }
+        }
+    }
+
+    Ok(Expression::Template(segments))
+}
+
 pub fn parse(input: &str) -> Result<Node, ParserErrors> {
-    let lexer = Lexer::new(input.to_string());
-    let mut parser = Parser::new(lexer);
+    parse_with_operators(input, &HashMap::new())
+}
+
+/// Parses `input` knowing the operators declared before it, keyed by
+/// symbol with their precedence level, as when a REPL line uses an
+/// operator an earlier line declared.
+pub fn parse_with_operators(input: &str, operators: &HashMap<String, u8>) -> Result<Node, ParserErrors> {
+    let operators = operators
+        .iter()
+        .filter_map(|(symbol, level)| Some((symbol.clone(), operator_precedence(i64::from(*level))?)))
+        .collect();
+    let mut parser = Parser::with_operators(input.to_string(), operators);
     let program = parser.parse_program()?;
 
     Ok(Node::Program(program))
@@ -342,25 +1228,108 @@ impl ParserError {
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
 pub enum Precedence {
     Lowest,
+    Assign,      // =, +=, -=, *= or /=
+    Pipeline,    // |>
+    Coalesce,    // ??
+    LogicalOr,   // ||
+    LogicalAnd,  // &&
     Equals,      // == or !=
-    LessGreater, // > or <
+    LessGreater, // >, <, >= or <=
+    Range,       // .. or ..=
+    BitOr,       // |
+    BitXor,      // ^
+    BitAnd,      // &
+    Shift,       // << or >>
     Sum,         // + or -
-    Product,     // * or /
+    Product,     // *, / or %
     Prefix,
     Call,
+    Index,
+    Member, // .
 }
 
 pub fn token_to_precedence(token: &Token) -> Precedence {
     match token {
-        Token::Asterisk | Token::ForwardSlash => Precedence::Product,
+        Token::Asterisk | Token::ForwardSlash | Token::Percent => Precedence::Product,
         Token::Plus | Token::Dash => Precedence::Sum,
-        Token::LessThan | Token::GreaterThan => Precedence::LessGreater,
-        Token::Equal | Token::NotEqual => Precedence::Equals,
-        Token::LParen => Precedence::Call,
+        Token::LessThan | Token::GreaterThan | Token::LessEqual | Token::GreaterEqual => {
+            Precedence::LessGreater
+        }
+        Token::Equal | Token::NotEqual | Token::Is => Precedence::Equals,
+        Token::DotDot | Token::DotDotEq => Precedence::Range,
+        Token::ShiftLeft | Token::ShiftRight => Precedence::Shift,
+        Token::Ampersand => Precedence::BitAnd,
+        Token::Caret => Precedence::BitXor,
+        Token::Pipe => Precedence::BitOr,
+        Token::And => Precedence::LogicalAnd,
+        Token::Or => Precedence::LogicalOr,
+        Token::NullCoalesce => Precedence::Coalesce,
+        Token::Pipeline => Precedence::Pipeline,
+        Token::Assign
+        | Token::PlusAssign
+        | Token::MinusAssign
+        | Token::AsteriskAssign
+        | Token::SlashAssign => Precedence::Assign,
+        Token::LParen | Token::QuestionDot => Precedence::Call,
+        Token::LBracket => Precedence::Index,
+        Token::Dot | Token::LBrace => Precedence::Member,
         _ => Precedence::Lowest,
     }
 }
 
+/// Rejects a symbol that code could already spell with built-in operators,
+/// such as `<-` in `x<-1` or `!!` in `!!done`: declaring it would change
+/// how that code reads. Every built-in operator after the first must then
+/// start an operand, as `-` and `!` do, while `<+>` can never be valid
+/// code because nothing follows `<` with `+`.
+fn check_operator_symbol(symbol: &str) -> Result<(), ParserError> {
+    let mut lexer = Lexer::new(symbol.to_string());
+    let mut tokens = Vec::new();
+    loop {
+        match lexer.next_token() {
+            Token::Eof => break,
+            // Built-in code never contains this character, so nothing reads
+            // differently once the symbol is declared
+            Token::Illegal(_) => return Ok(()),
+            token => tokens.push(token),
+        }
+    }
+
+    let starts_operand = |token: &Token| {
+        matches!(token, Token::Dash | Token::Bang | Token::Tilde | Token::Pipe | Token::Or)
+    };
+    if tokens.iter().skip(1).all(starts_operand) {
+        let tokens = tokens
+            .iter()
+            .map(|token| token.to_string())
+            .collect::<Vec<String>>()
+            .join(" ");
+        return Err(ParserError::new(format!(
+            "cannot declare operator {}: it already reads as {}",
+            symbol, tokens
+        )));
+    }
+    Ok(())
+}
+
+/// Maps the level of an `infix` declaration onto the built-in precedences,
+/// loosest first: 2 sits with `||`, 6 with `+` and 7 with `*`.
+fn operator_precedence(level: i64) -> Option<Precedence> {
+    let precedence = match level {
+        0 => Precedence::Pipeline,
+        1 => Precedence::Coalesce,
+        2 => Precedence::LogicalOr,
+        3 => Precedence::LogicalAnd,
+        4 => Precedence::LessGreater,
+        5 => Precedence::Range,
+        6 => Precedence::Sum,
+        7 => Precedence::Product,
+        8 => Precedence::Prefix,
+        _ => return None,
+    };
+    Some(precedence)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -380,6 +1349,13 @@ mod tests {
             ("let x = 5;", "let x = 5;"),
             ("let y = true;", "let y = true;"),
             ("let foobar = 124214;", "let foobar = 124214;"),
+            ("let [a, b] = pair;", "let [a, b] = pair;"),
+            ("let [head, ...tail] = list;", "let [head, ...tail] = list;"),
+            ("let [_, [x, y], ..._] = f();", "let [_, [x, y], ..._] = f();"),
+            ("let {name, age} = person;", "let {\"name\": name, \"age\": age} = person;"),
+            ("let {_, a} = h;", "let {\"_\": _, \"a\": a} = h;"),
+            ("let {\"a\": [x], 1: y, z} = h;", "let {\"a\": [x], 1: y, \"z\": z} = h;"),
+            ("let [] = xs;", "let [] = xs;"),
         ];
 
         apply_test(&test_case);
@@ -405,11 +1381,56 @@ mod tests {
 
     #[test]
     fn test_integer_literal_expression() {
-        let test_case = [("5;", "5")];
+        let test_case = [("5;", "5"), ("99999999999999999999", "99999999999999999999")];
+
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_float_literal_expression() {
+        let test_case = [
+            ("3.14;", "3.14"),
+            ("1e3", "1000.0"),
+            ("-2.5 * 2", "((-2.5) * 2)"),
+        ];
+        apply_test(&test_case);
+    }
 
+    #[test]
+    fn test_string_literal_expression() {
+        let test_case = [
+            ("\"hello world\";", "\"hello world\""),
+            ("let s = \"monkey\";", "let s = \"monkey\";"),
+            ("\"a\" + \"b\"", "(\"a\" + \"b\")"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        let test_case = [
+            ("\"hello ${name}\"", "\"hello ${name}\""),
+            ("\"${a + b * c}!\"", "\"${(a + (b * c))}!\""),
+            ("\"${add(1, 2)}\"", "\"${add(1, 2)}\""),
+        ];
         apply_test(&test_case);
     }
 
+    #[test]
+    fn test_lexer_errors() {
+        let test_case = [
+            ("\"${1 +}\"", "No prefix parse function for Eof is found"),
+            ("\"${a b}\"", "expected end of interpolation in \"${a b}\", but got Ident(b) instead"),
+            ("\"abc", "unterminated string"),
+        ];
+        for (input, expected) in test_case {
+            match parse(input) {
+                Ok(node) => panic!("expected parse error, got {}", node),
+                Err(errors) => assert_eq!(expected, errors[0].to_string()),
+            }
+        }
+    }
+
     #[test]
     fn test_boolean_literal_expression() {
         let test_case = [("true;", "true"), ("false;", "false")];
@@ -441,6 +1462,11 @@ mod tests {
             ("5 < 5;", "(5 < 5)"),
             ("5 == 5;", "(5 == 5)"),
             ("5 != 5;", "(5 != 5)"),
+            ("5 % 5;", "(5 % 5)"),
+            ("5 <= 5;", "(5 <= 5)"),
+            ("5 >= 5;", "(5 >= 5)"),
+            ("a && b;", "(a && b)"),
+            ("a || b;", "(a || b)"),
             ("true == true", "(true == true)"),
             ("true != false", "(true != false)"),
             ("false == false", "(false == false)"),
@@ -477,6 +1503,23 @@ mod tests {
             ("(5 + 5) * 2 * (5 + 5)", "(((5 + 5) * 2) * (5 + 5))"),
             ("-(5 + 5)", "(-(5 + 5))"),
             ("!(true == true)", "(!(true == true))"),
+            ("a + b % c", "(a + (b % c))"),
+            ("a <= b == c >= d", "((a <= b) == (c >= d))"),
+            ("a || b && c", "(a || (b && c))"),
+            ("a && b || c && d", "((a && b) || (c && d))"),
+            ("a == b && c < d", "((a == b) && (c < d))"),
+            ("!a && b", "((!a) && b)"),
+            ("a | b ^ c & d", "(a | (b ^ (c & d)))"),
+            ("a & b == c", "((a & b) == c)"),
+            ("1 << 2 + 3", "(1 << (2 + 3))"),
+            ("a >> 1 & 0xFF", "((a >> 1) & 255)"),
+            ("~a & b", "((~a) & b)"),
+            ("a | b || c", "((a | b) || c)"),
+            ("a * [1, 2, 3, 4][b * c] * d", "((a * ([1, 2, 3, 4][(b * c)])) * d)"),
+            (
+                "add(a * b[2], b[1], 2 * [1, 2][1])",
+                "add((a * (b[2])), (b[1]), (2 * ([1, 2][1])))",
+            ),
         ];
         apply_test(&test_case);
     }
@@ -493,12 +1536,383 @@ mod tests {
         apply_test(&test_case);
     }
 
+    #[test]
+    fn test_else_if_expression() {
+        let test_case = [
+            (
+                "if (x < 0) { -1 } else if (x > 0) { 1 } else { 0 }",
+                "if (x < 0) { (-1) } else { if (x > 0) { 1 } else { 0 } }",
+            ),
+            ("if (a) { 1 } else if (b) { 2 }", "if a { 1 } else { if b { 2 } }"),
+            (
+                "if (a) { 1 } else if (b) { 2 } else if (c) { 3 }",
+                "if a { 1 } else { if b { 2 } else { if c { 3 } } }",
+            ),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_match_expression() {
+        let test_case = [
+            (
+                "match (x) { 1 => \"one\", -2 => \"minus two\", _ => \"many\" }",
+                "match x { 1 => { \"one\" }, -2 => { \"minus two\" }, _ => { \"many\" } }",
+            ),
+            (
+                "match (p) { [a, b] => a + b, {\"k\": v, 1: [_, w]} => v, n if n > 5 => { n * 2 } }",
+                "match p { [a, b] => { (a + b) }, {\"k\": v, 1: [_, w]} => { v }, n if (n > 5) => { (n * 2) } }",
+            ),
+            ("match (x) { [] => null }", "match x { [] => { null } }"),
+            ("match (x) { }", "match x {  }"),
+            (
+                "match (xs) { [] => 0, [x, ...rest] => x, {kind} => kind }",
+                "match xs { [] => { 0 }, [x, ...rest] => { x }, {\"kind\": kind} => { kind } }",
+            ),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_pattern_errors() {
+        let test_case = [
+            ("match (x) { a + 1 => 1 }", "expected next token to be =>, but got + instead"),
+            ("match (x) { (1) => 1 }", "expected a pattern but got LParen"),
+            ("match (x) { {a: 1} => 1 }", "invalid hash pattern key: a"),
+            ("match (x) { 1 => 1", "expected a pattern but got Eof"),
+            ("let [...a, b] = xs;", "a rest pattern must be the last element"),
+            ("let [...[a]] = xs;", "invalid rest pattern: ...[a]"),
+            ("let {a: 1} = xs;", "invalid hash pattern key: a"),
+            ("let 5 = x;", "Expected an identifier but got Int(5)"),
+            ("fn(...a, b) {}", "a rest parameter must be the last parameter"),
+            ("fn(...1) {}", "Expected an identifier but got Int(1)"),
+            ("try { 1 }", "expected catch or finally after try block"),
+            ("import s as s;", "expected a module path but got Ident(s)"),
+            ("import \"s.mk\";", "expected next token to be As, but got Semicolon instead"),
+            ("fn() { export let x = 1; }", "exports are only allowed at the top level"),
+            ("export fn() {}", "expected let, struct or enum after export but got Function"),
+            ("export infix 6 <+> = f;", "expected let, struct or enum after export but got Infix"),
+            ("a.1", "Expected an identifier but got Int(1)"),
+            ("struct P { x, x }", "duplicate member x in struct P"),
+            ("enum E { A, A(x) }", "duplicate variant A in enum E"),
+            ("infix 9 <+> = f;", "invalid operator precedence 9: expected 0 to 8"),
+            ("infix x <+> = f;", "expected an operator precedence but got Ident(x)"),
+            ("infix 6 + = f;", "expected an operator symbol but got +"),
+            ("infix 6 <+> f;", "expected next token to be =, but got Ident(f) instead"),
+            ("fn() { infix 6 <+> = f; }", "operators can only be declared at the top level"),
+            ("infix 6 <- = f;", "cannot declare operator <-: it already reads as < -"),
+            ("infix 6 !! = f;", "cannot declare operator !!: it already reads as ! !"),
+            ("infix 6 =-~ = f;", "cannot declare operator =-~: it already reads as = - ~"),
+            ("infix 6 <|| = f;", "cannot declare operator <||: it already reads as < ||"),
+            ("infix 6 <+>// = f;", "expected next token to be =, but got Eof instead"),
+            ("enum E { A(1) }", "Expected an identifier but got Int(1)"),
+            ("enum E { A B }", "expected next token to be Comma, but got Ident(B) instead"),
+            ("let E.1 = x;", "Expected an identifier but got Int(1)"),
+            ("struct P { x, fn x() {} }", "duplicate member x in struct P"),
+            ("struct P { x, fn len(self) { 1 } }", "method len in struct P cannot declare a self parameter"),
+            ("struct P { fn f(a, self = 1) { a } }", "method f in struct P cannot declare a self parameter"),
+            ("struct P { 1 }", "expected a field or method in struct P but got Int(1)"),
+            ("P { x: 1 y: 2 }", "expected next token to be Comma, but got Ident(y) instead"),
+            ("try { 1 } catch (1) { 2 }", "Expected an identifier but got Int(1)"),
+        ];
+        for (input, expected) in test_case {
+            match parse(input) {
+                Ok(node) => panic!("expected parse error, got {}", node),
+                Err(errors) => assert_eq!(expected, errors[0].to_string()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_try_expression() {
+        let test_case = [
+            ("try { f() } catch (e) { e }", "try { f() } catch (e) { e }"),
+            ("try { f() } finally { cleanup() }", "try { f() } finally { cleanup() }"),
+            (
+                "try { throw \"oops\"; } catch { 1 } finally { 2 }",
+                "try { throw \"oops\"; } catch { 1 } finally { 2 }",
+            ),
+            ("throw error(\"bad\", \"ValueError\")", "throw error(\"bad\", \"ValueError\");"),
+            ("let x = try { 1 } catch (e) { 2 };", "let x = try { 1 } catch (e) { 2 };"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_modules() {
+        let test_case = [
+            ("import \"lib/strings.mk\" as s;", "import \"lib/strings.mk\" as s;"),
+            ("export let x = 1;", "export let x = 1;"),
+            ("export let [a, ...b] = xs;", "export let [a, ...b] = xs;"),
+            ("export struct P { x }", "export struct P { x }"),
+            ("export enum E { A, B(x) }", "export enum E { A, B(x) }"),
+            ("s.shout(\"hi\")", "(s.shout)(\"hi\")"),
+            ("a.b.c[0]", "(((a.b).c)[0])"),
+            ("-s.x * 2", "((-(s.x)) * 2)"),
+            ("xs |> s.join(\",\")", "(s.join)(xs, \",\")"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_structs() {
+        let test_case = [
+            ("struct Point { x, y }", "struct Point { x, y }"),
+            (
+                "struct Point { x, y, fn norm() { self.x + self.y } fn scale(by = 2) { by } }",
+                "struct Point { x, y, fn norm() {...}, fn scale(by = 2) {...} }",
+            ),
+            ("struct Empty {}", "struct Empty {  }"),
+            ("Point { x: 1, y: 2 + 3 }", "Point { x: 1, y: (2 + 3) }"),
+            ("Point { x, y: 2 }", "Point { x: x, y: 2 }"),
+            ("Point { x: 1 }.x", "(Point { x: 1 }.x)"),
+            ("m.Point { x: 1 }", "(m.Point) { x: 1 }"),
+            ("-Point { x: 1 }.x", "(-(Point { x: 1 }.x))"),
+            ("a + Point { x: 1 }.x * 2", "(a + ((Point { x: 1 }.x) * 2))"),
+            ("f(Point { x: 1 })", "f(Point { x: 1 })"),
+            ("match (p) { n if n == (P { x: q }).x => 1 }", "match p { n if (n == (P { x: q }.x)) => { 1 } }"),
+            ("match (p) { n if n == P { x: q }.x => 1 }", "match p { n if (n == (P { x: q }.x)) => { 1 } }"),
+            ("p.norm() * 2", "((p.norm)() * 2)"),
+            ("Unit {}", "Unit {  }"),
+            ("if (p) { 1 }", "if p { 1 }"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_enums() {
+        let test_case = [
+            ("enum Shape { Circle(r), Rect(w, h), Empty }", "enum Shape { Circle(r), Rect(w, h), Empty }"),
+            ("enum Option { Some(value), None, }", "enum Option { Some(value), None }"),
+            ("enum Unit { A() }", "enum Unit { A }"),
+            ("Shape.Circle(3)", "(Shape.Circle)(3)"),
+            ("s is Shape.Circle", "(s is (Shape.Circle))"),
+            ("s is Shape.Empty == !done", "((s is (Shape.Empty)) == (!done))"),
+            ("a + 1 is T", "((a + 1) is T)"),
+            ("let Shape.Rect(w, h) = s;", "let Shape.Rect(w, h) = s;"),
+            ("let Option.None = s;", "let Option.None = s;"),
+            (
+                "match (s) { Shape.Circle(r) => r, Shape.Rect(w, [h, _]) if w > 0 => w, Shape.Empty => 0 }",
+                "match s { Shape.Circle(r) => { r }, Shape.Rect(w, [h, _]) if (w > 0) => { w }, Shape.Empty => { 0 } }",
+            ),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_infix_operators() {
+        let test_case = [
+            ("infix 6 <+> = fn(a, b) { a + b };", "infix 6 <+> = fn(a, b) {...};"),
+            ("infix 6 <+> = f; a <+> b <+> c", "infix 6 <+> = f;((a <+> b) <+> c)"),
+            ("infix 6 <+> = f; a <+> b * c", "infix 6 <+> = f;(a <+> (b * c))"),
+            ("infix 6 <+> = f; a * b <+> c - d", "infix 6 <+> = f;(((a * b) <+> c) - d)"),
+            (
+                "infix 6 <+> = f; infix 7 <*> = g; a <+> b <*> c",
+                "infix 6 <+> = f;infix 7 <*> = g;(a <+> (b <*> c))",
+            ),
+            ("infix 2 @@ = either; a == b @@ c", "infix 2 @@ = either;((a == b) @@ c)"),
+            ("infix 8 ** = pow; -a ** b + 1", "infix 8 ** = pow;(((-a) ** b) + 1)"),
+            ("infix 4 <=> = cmp; a <=> b == 0", "infix 4 <=> = cmp;((a <=> b) == 0)"),
+            ("infix 6 |+| = add; a |+| b", "infix 6 |+| = add;(a |+| b)"),
+            ("infix 6 <+> = f; \"${a <+> b}\"", "infix 6 <+> = f;\"${(a <+> b)}\""),
+            // Each parse starts from the operators it is given
+            ("a <= b", "(a <= b)"),
+            ("a <+> b", "No prefix parse function for + is found"),
+        ];
+        for (input, expected) in test_case {
+            match parse(input) {
+                Ok(node) => assert_eq!(expected, &format!("{}", node)),
+                Err(errors) => assert_eq!(expected, &errors[0].to_string()),
+            }
+        }
+
+        let operators = HashMap::from([("<+>".to_string(), 6), ("<*>".to_string(), 7)]);
+        let test_case = [
+            ("a <+> b <*> c", "(a <+> (b <*> c))"),
+            ("a <*> b <+> c", "((a <*> b) <+> c)"),
+            ("a < b", "(a < b)"),
+        ];
+        for (input, expected) in test_case {
+            match parse_with_operators(input, &operators) {
+                Ok(node) => assert_eq!(expected, &format!("{}", node)),
+                Err(e) => panic!("Parsing Error: {:#?}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_while_expression() {
+        let test_case = [
+            ("while (x < 10) { x += 1; }", "while (x < 10) { (x += 1) }"),
+            (
+                "while (true) { if (x) { break; } continue; }",
+                "while true { if x { break; }continue; }",
+            ),
+            ("while (a) { while (b) { break; } break; }", "while a { while b { break; }break; }"),
+            ("while (a) { fn() { 1 }; continue }", "while a { fn() {...}continue; }"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_for_expression() {
+        let test_case = [
+            ("for (x in xs) { puts(x); }", "for x in xs { puts(x) }"),
+            ("for (k, v in h) { k }", "for k, v in h { k }"),
+            ("for (i in 0..10) { if (i > 5) { break; } }", "for i in (0 .. 10) { if (i > 5) { break; } }"),
+            ("0..=n + 1", "(0 ..= (n + 1))"),
+            ("a..b == c..d", "((a .. b) == (c .. d))"),
+            ("0..n | 1", "(0 .. (n | 1))"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_loop_control_outside_loop() {
+        let test_case = [
+            ("break;", "break outside of a loop"),
+            ("continue;", "continue outside of a loop"),
+            ("if (x) { break; }", "break outside of a loop"),
+            ("while (x) { fn() { break; } }", "break outside of a loop"),
+            ("while (x) { 1 }; continue;", "continue outside of a loop"),
+            ("for (x in xs) { fn() { continue; } }", "continue outside of a loop"),
+            ("while (true) { let f = fn(x = if (true) { break; }) { x }; f() }", "break outside of a loop"),
+            ("while (true) { |y = if (true) { continue; }| y }", "continue outside of a loop"),
+            ("for (i in xs) { fn(x = match (1) { _ => { break; } }) { x } }", "break outside of a loop"),
+            ("for (a, b, c in xs) { 1 }", "expected next token to be In, but got Comma instead"),
+        ];
+        for (input, expected) in test_case {
+            match parse(input) {
+                Ok(node) => panic!("expected parse error, got {}", node),
+                Err(errors) => assert_eq!(expected, errors[0].to_string()),
+            }
+        }
+    }
+
     #[test]
     fn test_function_expression() {
         let test_case = [
             ("fn() {};", "fn() {...}"),
             ("fn(x) {};", "fn(x) {...}"),
             ("fn(x, y, z) {};", "fn(x, y, z) {...}"),
+            ("fn(x, y = 10) {};", "fn(x, y = 10) {...}"),
+            ("fn(x = a + 1, ...rest) {};", "fn(x = (a + 1), ...rest) {...}"),
+            ("fn(...all) {};", "fn(...all) {...}"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_spread_arguments() {
+        let test_case = [
+            ("f(...args)", "f(...args)"),
+            ("f(1, ...xs, ...ys + zs)", "f(1, ...xs, ...(ys + zs))"),
+            ("f?.(...xs)", "f?.(...xs)"),
+            ("[0, ...xs]", "[0, ...xs]"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_lambda_expression() {
+        let test_case = [
+            ("|x| x * 2", "fn(x) {...}"),
+            ("|a, b = 1, ...c| a", "fn(a, b = 1, ...c) {...}"),
+            ("|x = a + 1| x", "fn(x = (a + 1)) {...}"),
+            ("|| 42", "fn() {...}"),
+            ("x => x * 2", "fn(x) {...}"),
+            ("map(xs, |x| x + 1)", "map(xs, fn(x) {...})"),
+            ("map(xs, x => x + 1, 2)", "map(xs, fn(x) {...}, 2)"),
+            ("|x| { let y = x; y }", "fn(x) {...}"),
+            ("let f = |x| x | 1;", "let f = fn(x) {...};"),
+            ("match (x) { n if ok => n }", "match x { n if ok => { n } }"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_pipeline_expression() {
+        let test_case = [
+            ("x |> f", "f(x)"),
+            ("x |> f(y)", "f(x, y)"),
+            ("x |> f(a)()", "f(x, a)()"),
+            ("x |> f(a)(b)(c)", "f(x, a)(b)(c)"),
+            ("x |> f?.(a)()", "f?.(x, a)()"),
+            ("data |> filter(isOdd) |> map(double)", "map(filter(data, isOdd), double)"),
+            ("a + 1 |> f", "f((a + 1))"),
+            ("x |> g?.(1)", "g?.(x, 1)"),
+            ("xs |> map(|x| x * 2)", "map(xs, fn(x) {...})"),
+            ("y = x |> f", "(y = f(x))"),
+            ("x ?? d |> f", "f((x ?? d))"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_array_literal() {
+        let test_case = [
+            ("[]", "[]"),
+            ("[1, 2 * 2, 3 + 3]", "[1, (2 * 2), (3 + 3)]"),
+            ("[\"a\", [true]]", "[\"a\", [true]]"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_hash_literal() {
+        let test_case = [
+            ("{}", "{}"),
+            ("{\"one\": 1, \"two\": 2}", "{\"one\": 1, \"two\": 2}"),
+            ("{1: true, false: \"x\",}", "{1: true, false: \"x\"}"),
+            ("{\"a\": 0 + 1, \"b\": 10 / 5}", "{\"a\": (0 + 1), \"b\": (10 / 5)}"),
+            ("if (x) { {\"k\": x} }", "if x { {\"k\": x} }"),
+            ("fn() { {} }", "fn() {...}"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_null_expressions() {
+        let test_case = [
+            ("null", "null"),
+            ("let x = null;", "let x = null;"),
+            ("a ?? b", "(a ?? b)"),
+            ("a ?? b ?? c", "((a ?? b) ?? c)"),
+            ("a || b ?? c && d", "((a || b) ?? (c && d))"),
+            ("f?.(1, 2)", "f?.(1, 2)"),
+            ("f?.()", "f?.()"),
+            ("obj[\"f\"]?.(x) ?? 0", "((obj[\"f\"])?.(x) ?? 0)"),
+        ];
+        apply_test(&test_case);
+    }
+
+    #[test]
+    fn test_assign_expression() {
+        let test_case = [
+            ("x = 5", "(x = 5)"),
+            ("x = y + 1;", "(x = (y + 1))"),
+            ("x += 2 * 3", "(x += (2 * 3))"),
+            ("x -= 1; x *= 2; x /= 3", "(x -= 1)(x *= 2)(x /= 3)"),
+            ("a = b = c", "(a = (b = c))"),
+            ("x = y ?? 0", "(x = (y ?? 0))"),
+            ("fn() { count = count + 1; }", "fn() {...}"),
+        ];
+        apply_test(&test_case);
+
+        for input in ["1 = 2", "a + b = c", "f() += 1"] {
+            match parse(input) {
+                Ok(node) => panic!("expected parse error, got {}", node),
+                Err(errors) => assert!(errors[0].to_string().starts_with("invalid assignment target")),
+            }
+        }
+    }
+
+    #[test]
+    fn test_index_expression() {
+        let test_case = [
+            ("myArray[1 + 1]", "(myArray[(1 + 1)])"),
+            ("a[0][1]", "((a[0])[1])"),
+            ("f(x)[0]", "(f(x)[0])"),
         ];
         apply_test(&test_case);
     }
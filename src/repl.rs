@@ -1,7 +1,9 @@
-use crate::parser::*;
-use crate::evaluator::*;
-use crate::environment::*;
+use monkey_lang::parser::*;
+use monkey_lang::evaluator::*;
+use monkey_lang::environment::*;
+use monkey_lang::module::Modules;
 use std::rc::Rc;
+use std::cell::RefCell;
 use std::io::Write;
 
 const MONKEY_FACE: &str = r#"            __,__
@@ -17,20 +19,21 @@ const MONKEY_FACE: &str = r#"            __,__
            '-----'
 "#;
 
-pub fn start() {
+pub fn start(modules: Modules) {
 
-    let env: Env = Rc::new(Default::default());
+    let env: Env = Rc::new(RefCell::new(Environment::with_modules(Rc::new(modules))));
 
     print!(">> ");
     std::io::stdout().flush().expect("can't flush stdout");
     std::io::stdin().lines().for_each(|line| {
         if let Ok(line) = line {
-            match parse(&line) {
+            let operators = env.borrow().operators();
+            match parse_with_operators(&line, &operators) {
                 Ok(node) => match eval(node, &Rc::clone(&env)) {
                         Ok(evaluated) => {
                             println!("{}", evaluated)
                         }
-                        Err(err) => eprintln!("{}", err),
+                        Err(err) => eprintln!("{}: {}", err.kind, err),
                 },
                 Err(errors) => {
                     eprintln!("{}", MONKEY_FACE);
@@ -1,66 +1,190 @@
+use crate::bigint::BigInt;
 use std::fmt::Display;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
     Ident(String),
-    Integer(i32),
+    Integer(i64),
+    BigInt(BigInt),
+    Float(f64),
+    String(String),
+    Template(Vec<TemplatePart>),
     True,
     False,
-    Illegal,
+    Null,
+    Illegal(String),
+    // A symbolic operator declared with `infix`, such as `<+>`
+    Operator(String),
+    Comment(String),
     Eof,
     Equal,
     Plus,
     Comma,
+    Colon,
     Semicolon,
     LParen,
     RParen,
     LBrace,
     RBrace,
+    LBracket,
+    RBracket,
     Function,
     Let,
     Assign,
+    PlusAssign,
+    MinusAssign,
+    AsteriskAssign,
+    SlashAssign,
     Bang,
     Dash,
     ForwardSlash,
     Asterisk,
+    Percent,
     NotEqual,
     LessThan,
     GreaterThan,
+    LessEqual,
+    GreaterEqual,
+    And,
+    Or,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
+    ShiftLeft,
+    ShiftRight,
+    NullCoalesce,
+    QuestionDot,
+    DotDot,
+    DotDotEq,
+    Ellipsis,
+    Dot,
+    FatArrow,
+    Pipeline,
     Return,
     If,
     Else,
+    Match,
+    While,
+    For,
+    In,
+    Break,
+    Continue,
+    Throw,
+    Try,
+    Catch,
+    Finally,
+    Import,
+    Export,
+    As,
+    Struct,
+    Enum,
+    Is,
+    Infix,
 }
 
 impl Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        return match self {
+        match self {
             Token::Ident(x) => write!(f, "Ident({})", x),
             Token::Integer(x) => write!(f, "Int({})", x),
-            Token::Illegal => write!(f, "Illegal"),
+            Token::BigInt(x) => write!(f, "Int({})", x),
+            Token::Float(x) => write!(f, "Float({})", x),
+            Token::String(x) => write!(f, "String({})", x),
+            Token::Template(parts) => write!(f, "Template({} parts)", parts.len()),
+            Token::Illegal(x) => write!(f, "Illegal({})", x),
+            Token::Comment(x) => write!(f, "Comment({})", x),
             Token::Eof => write!(f, "Eof"),
             Token::Assign => write!(f, "="),
+            Token::PlusAssign => write!(f, "+="),
+            Token::MinusAssign => write!(f, "-="),
+            Token::AsteriskAssign => write!(f, "*="),
+            Token::SlashAssign => write!(f, "/="),
             Token::Bang => write!(f, "!"),
             Token::Dash => write!(f, "-"),
             Token::ForwardSlash => write!(f, "/"),
             Token::Asterisk => write!(f, "*"),
+            Token::Percent => write!(f, "%"),
             Token::Equal => write!(f, "=="),
             Token::NotEqual => write!(f, "!="),
             Token::LessThan => write!(f, "<"),
             Token::GreaterThan => write!(f, ">"),
+            Token::LessEqual => write!(f, "<="),
+            Token::GreaterEqual => write!(f, ">="),
+            Token::And => write!(f, "&&"),
+            Token::Or => write!(f, "||"),
+            Token::Ampersand => write!(f, "&"),
+            Token::Pipe => write!(f, "|"),
+            Token::Caret => write!(f, "^"),
+            Token::Tilde => write!(f, "~"),
+            Token::ShiftLeft => write!(f, "<<"),
+            Token::ShiftRight => write!(f, ">>"),
+            Token::NullCoalesce => write!(f, "??"),
+            Token::QuestionDot => write!(f, "?."),
+            Token::DotDot => write!(f, ".."),
+            Token::DotDotEq => write!(f, "..="),
+            Token::Ellipsis => write!(f, "..."),
+            Token::Dot => write!(f, "."),
+            Token::FatArrow => write!(f, "=>"),
+            Token::Pipeline => write!(f, "|>"),
             Token::Plus => write!(f, "+"),
             Token::Comma => write!(f, "Comma"),
+            Token::Colon => write!(f, "Colon"),
             Token::Semicolon => write!(f, "Semicolon"),
             Token::LParen => write!(f, "LParen"),
             Token::RParen => write!(f, "RParen"),
             Token::LBrace => write!(f, "LBrace"),
             Token::RBrace => write!(f, "RBrace"),
+            Token::LBracket => write!(f, "LBracket"),
+            Token::RBracket => write!(f, "RBracket"),
             Token::Function => write!(f, "Function"),
             Token::Let => write!(f, "Let"),
             Token::If => write!(f, "If"),
             Token::Else => write!(f, "Else"),
+            Token::Match => write!(f, "Match"),
+            Token::While => write!(f, "While"),
+            Token::For => write!(f, "For"),
+            Token::In => write!(f, "In"),
+            Token::Break => write!(f, "Break"),
+            Token::Continue => write!(f, "Continue"),
+            Token::Throw => write!(f, "Throw"),
+            Token::Try => write!(f, "Try"),
+            Token::Catch => write!(f, "Catch"),
+            Token::Finally => write!(f, "Finally"),
+            Token::Import => write!(f, "Import"),
+            Token::Export => write!(f, "Export"),
+            Token::As => write!(f, "As"),
+            Token::Struct => write!(f, "Struct"),
+            Token::Enum => write!(f, "Enum"),
+            Token::Is => write!(f, "Is"),
+            Token::Infix => write!(f, "Infix"),
+            Token::Operator(symbol) => write!(f, "{}", symbol),
             Token::Return => write!(f, "Return"),
             Token::True => write!(f, "true"),
             Token::False => write!(f, "false"),
+            Token::Null => write!(f, "null"),
+        }
+    }
+}
+
+/// A piece of an interpolated string literal: either literal text or the
+/// source code of an embedded `${...}` expression.
+#[derive(Debug, PartialEq, Clone)]
+pub enum TemplatePart {
+    Text(String),
+    Code(String),
+}
+
+impl Token {
+    /// Maps a compound assignment operator such as `+=` to the infix
+    /// operator it applies.
+    pub fn compound_operator(&self) -> Option<Token> {
+        match self {
+            Token::PlusAssign => Some(Token::Plus),
+            Token::MinusAssign => Some(Token::Dash),
+            Token::AsteriskAssign => Some(Token::Asterisk),
+            Token::SlashAssign => Some(Token::ForwardSlash),
+            _ => None,
         }
     }
 }